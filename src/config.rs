@@ -0,0 +1,344 @@
+/// How spaces are written when percent-encoding keys and values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpaceEncoding {
+    /// Spaces are written as `+`, as browsers do when submitting a form.
+    #[default]
+    Plus,
+    /// Spaces are written as `%20`.
+    Percent,
+}
+
+/// How strings are written by the serializer and read by the deserializer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringStyle {
+    /// Strings are written bare and percent-encoded (`key=some+value`), as in any HTML form body.
+    #[default]
+    Plain,
+    /// Strings are wrapped in double quotes (`key="some+value"`).
+    ///
+    /// When deserializing, `"` delimits a string in which `&` and `=` are not separators,
+    /// and `\` escapes the next character.
+    Quoted,
+}
+
+/// How sequences are written by the serializer and read by the deserializer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SequenceStyle {
+    /// Items are keyed by their index, along with the length of the sequence (`tag.0=a&tag.1=b&tag.$length=2`).
+    #[default]
+    Indexed,
+    /// Items are written as repeated keys (`tag=a&tag=b`), as browsers do for multi-selects.
+    ///
+    /// When deserializing, repeated keys are collected into a sequence and a key found once
+    /// is read as a sequence of one item. Indexed sequences are still accepted.
+    /// When serializing, only sequences of single values are written as repeated keys,
+    /// and an empty sequence is not written at all.
+    ///
+    /// The sequences of flattened structs and enums are read as a single value when found once,
+    /// and should use [`repeated`](crate::repeated) (`#[serde(with = "serde_www_form_urlencoded::repeated")]`).
+    Repeated,
+    /// Items are joined by the delimiter in a single value (`ids=1,2,3`).
+    ///
+    /// When deserializing, a single value is split on the delimiter, an empty value being an
+    /// empty sequence. Indexed sequences are still accepted. When serializing, only non-empty
+    /// sequences of single values are joined, an item containing the delimiter being an error.
+    Delimited(char),
+}
+
+/// How the length of an indexed sequence is known.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SequenceLength {
+    /// The length is written in a [length key](Config::length_key), which is required when deserializing.
+    #[default]
+    Explicit,
+    /// No length key is written, the length is inferred from the indices when deserializing.
+    ///
+    /// A length key is still accepted when present. Unless `allow_gaps` is set, a missing index
+    /// is an error, otherwise the items found are read in the order of their indices.
+    /// As nothing is written for an empty sequence, such fields should have a default value.
+    Inferred { allow_gaps: bool },
+}
+
+/// How nested maps and structs are written by the serializer and read by the deserializer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ObjectStyle {
+    /// Each entry is written under its own nested key (`id.role=admin`).
+    #[default]
+    Nested,
+    /// Keys and values are joined by the delimiter in a single value (`id=role,admin,name,Alex`).
+    ///
+    /// When serializing, only non-empty maps of single values are joined, a key or value containing
+    /// the delimiter being an error. Nested keys are still accepted when deserializing.
+    Delimited(char),
+    /// The entries of the maps nested in the top-level one are written without their name (`role=admin`).
+    ///
+    /// Such maps are read back with `#[serde(flatten)]`, as their name is not in the input. Their
+    /// sequences should then use [`repeated`](crate::repeated), or [`delimited`](crate::delimited)
+    /// with delimited sequences.
+    Flattened,
+}
+
+/// An OpenAPI query parameter style, see [`Config::parameter_style`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterStyle {
+    /// `id=3&id=4` and `role=admin` when exploded, `id=3,4` and `filter=role,admin` otherwise.
+    ///
+    /// Exploded objects are read back with `#[serde(flatten)]`, their sequences using
+    /// [`repeated`](crate::repeated) so that a single item (`tags=red`) is accepted.
+    Form,
+    /// `id=3&id=4` when exploded, `id=3%204` otherwise.
+    SpaceDelimited,
+    /// `id=3&id=4` when exploded, `id=3|4` otherwise.
+    PipeDelimited,
+    /// `id[role]=admin`.
+    DeepObject,
+}
+
+/// How the path of a nested value is written in its key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeySyntax {
+    /// Segments are separated by the [separator](Config::separator), a dot by default (`user.name`, `tags.0`).
+    #[default]
+    Dotted,
+    /// Segments are enclosed in brackets (`user[name]`, `tags[0]`), as PHP and Rails do.
+    ///
+    /// When deserializing, `tags[]` pushes its value into a sequence. When serializing,
+    /// repeated keys are written with this push notation.
+    Bracket,
+}
+
+/// Serialization and deserialization options.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_www_form_urlencoded::{Config, SpaceEncoding};
+///
+/// #[derive(Serialize)]
+/// struct Token<'a> {
+///   scope: &'a str
+/// }
+///
+/// let config = Config::new().space_encoding(SpaceEncoding::Percent);
+///
+/// assert_eq!(
+///     config.to_string(&Token { scope: "read write" }).unwrap(),
+///     "scope=read%20write"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub(crate) space_encoding: SpaceEncoding,
+    pub(crate) string_style: StringStyle,
+    pub(crate) lenient: bool,
+    pub(crate) sequence_style: SequenceStyle,
+    pub(crate) key_syntax: KeySyntax,
+    pub(crate) sequence_length: SequenceLength,
+    pub(crate) separator: &'static str,
+    pub(crate) length_key: &'static str,
+    pub(crate) object_style: ObjectStyle,
+    pub(crate) infer_types: bool,
+    pub(crate) null_as_none: bool,
+    pub(crate) empty_as_none: bool,
+    pub(crate) skip_none: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            space_encoding: Default::default(),
+            string_style: Default::default(),
+            lenient: false,
+            sequence_style: Default::default(),
+            key_syntax: Default::default(),
+            sequence_length: Default::default(),
+            separator: ".",
+            length_key: "$length",
+            object_style: Default::default(),
+            infer_types: true,
+            null_as_none: true,
+            empty_as_none: false,
+            skip_none: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how spaces are written by the serializer, `+` by default.
+    ///
+    /// Both forms are always accepted by the deserializer.
+    pub fn space_encoding(mut self, space_encoding: SpaceEncoding) -> Self {
+        self.space_encoding = space_encoding;
+        self
+    }
+
+    /// Set how strings are written and read, [`StringStyle::Plain`] by default.
+    pub fn string_style(mut self, string_style: StringStyle) -> Self {
+        self.string_style = string_style;
+        self
+    }
+
+    /// Parse real-world query strings the way browsers do, disabled by default.
+    ///
+    /// When enabled, the deserializer follows the WHATWG urlencoded parser:
+    /// - a leading `?` is stripped,
+    /// - empty pairs (`a=1&&b=2`, trailing `&`) are skipped,
+    /// - a key without `=` (`debug`) is read as an empty string, or `true` for a bool,
+    /// - any `=` after the first one of a pair is part of the value (`a=b=c`).
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Set how sequences are written and read, [`SequenceStyle::Indexed`] by default.
+    pub fn sequence_style(mut self, sequence_style: SequenceStyle) -> Self {
+        self.sequence_style = sequence_style;
+        self
+    }
+
+    /// Set how the length of indexed sequences is known, [`SequenceLength::Explicit`] by default.
+    pub fn sequence_length(mut self, sequence_length: SequenceLength) -> Self {
+        self.sequence_length = sequence_length;
+        self
+    }
+
+    /// Set the separator between the segments of a dotted key, `.` by default.
+    ///
+    /// Other separators such as `__`, `:` or `/` allow field names to contain dots.
+    ///
+    /// A key segment containing the separator is escaped with backslashes when serializing.
+    ///
+    /// # Panics
+    /// Panics if the separator is empty, contains a backslash or starts with `~`, which escapes empty segments.
+    pub fn separator(mut self, separator: &'static str) -> Self {
+        assert!(!separator.is_empty(), "the separator cannot be empty");
+        assert!(!separator.contains('\\'), "the separator cannot contain a backslash");
+        assert!(!separator.starts_with('~'), "the separator cannot start with `~`");
+        self.separator = separator;
+        self
+    }
+
+    /// Set the name of the key holding the length of a sequence, `$length` by default.
+    ///
+    /// # Panics
+    /// Panics if the length key is empty or `~`, which escapes empty segments.
+    pub fn length_key(mut self, length_key: &'static str) -> Self {
+        assert!(!length_key.is_empty() && length_key != "~", "the length key cannot be empty or `~`");
+        self.length_key = length_key;
+        self
+    }
+
+    /// Set how nested maps and structs are written and read, [`ObjectStyle::Nested`] by default.
+    pub fn object_style(mut self, object_style: ObjectStyle) -> Self {
+        self.object_style = object_style;
+        self
+    }
+
+    /// Follow an OpenAPI query parameter style, each field of the serialized struct being a parameter.
+    ///
+    /// This sets the [sequence style](Config::sequence_style), [object style](Config::object_style)
+    /// and [key syntax](Config::key_syntax), and percent-encodes spaces for `spaceDelimited`.
+    /// As specified, exploded objects of the `form` style are written without their name
+    /// ([`ObjectStyle::Flattened`]), and are read back with `#[serde(flatten)]`.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_www_form_urlencoded::{Config, ParameterStyle};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Filter {
+    ///   role: &'static str,
+    ///   name: &'static str
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Query {
+    ///   id: Vec<u32>,
+    ///   filter: Filter
+    /// }
+    ///
+    /// let query = Query { id: vec![3, 4, 5], filter: Filter { role: "admin", name: "Alex" } };
+    ///
+    /// let config = Config::new().parameter_style(ParameterStyle::Form, false);
+    /// assert_eq!(config.to_string(&query).unwrap(), "id=3,4,5&filter=role,admin,name,Alex");
+    ///
+    /// let config = Config::new().parameter_style(ParameterStyle::DeepObject, true);
+    /// assert_eq!(config.to_string(&query).unwrap(), "id%5B%5D=3&id%5B%5D=4&id%5B%5D=5&filter%5Brole%5D=admin&filter%5Bname%5D=Alex");
+    /// ```
+    pub fn parameter_style(self, style: ParameterStyle, explode: bool) -> Self {
+        let (sequence_style, object_style) = match (style, explode) {
+            (ParameterStyle::Form, false) => (SequenceStyle::Delimited(','), ObjectStyle::Delimited(',')),
+            (ParameterStyle::SpaceDelimited, false) => (SequenceStyle::Delimited(' '), ObjectStyle::Delimited(' ')),
+            (ParameterStyle::PipeDelimited, false) => (SequenceStyle::Delimited('|'), ObjectStyle::Delimited('|')),
+            (ParameterStyle::Form, true) => (SequenceStyle::Repeated, ObjectStyle::Flattened),
+            _ => (SequenceStyle::Repeated, ObjectStyle::Nested),
+        };
+        let space_encoding = match style {
+            ParameterStyle::SpaceDelimited => SpaceEncoding::Percent,
+            _ => self.space_encoding,
+        };
+        let key_syntax = match style {
+            ParameterStyle::DeepObject => KeySyntax::Bracket,
+            _ => KeySyntax::Dotted,
+        };
+
+        self.sequence_style(sequence_style)
+            .object_style(object_style)
+            .key_syntax(key_syntax)
+            .space_encoding(space_encoding)
+    }
+
+    /// Set how the path of nested values is written in keys, [`KeySyntax::Dotted`] by default.
+    pub fn key_syntax(mut self, key_syntax: KeySyntax) -> Self {
+        self.key_syntax = key_syntax;
+        self
+    }
+
+    /// Infer the type of values when the target type is not known, enabled by default.
+    ///
+    /// This is the case of untagged and internally tagged enums, flattened fields, or dynamic
+    /// types such as `serde_json::Value`. When enabled:
+    /// - `true` and `false` are read as bools, and `null` as a unit,
+    /// - integers written in their canonical form (`42`, `-1`, but not `007`) and decimal literals
+    ///   (`1.5`, `2.50`, `1e3`) as numbers,
+    /// - maps keyed by the indices `0` to `n - 1` as sequences, even without a length key.
+    ///
+    /// Otherwise, values are read as strings and only maps with a length key as sequences.
+    /// A [`Value`](crate::Value) is always read as written.
+    ///
+    /// As serde cannot read an inferred number, bool or unit back into a string, a `String` field
+    /// reached this way fails on values such as `name=42` or `name=true`. Such fields should use
+    /// [`string`](crate::string) (`#[serde(with = "serde_www_form_urlencoded::string")]`).
+    pub fn infer_types(mut self, infer_types: bool) -> Self {
+        self.infer_types = infer_types;
+        self
+    }
+
+    /// Read the `null` token as `None`, enabled by default.
+    ///
+    /// When disabled, `x=null` is read as `Some("null")`, which should be paired with
+    /// [`skip_none`](Config::skip_none) so that `None` round-trips.
+    pub fn null_as_none(mut self, null_as_none: bool) -> Self {
+        self.null_as_none = null_as_none;
+        self
+    }
+
+    /// Read an empty value (`x=`) as `None`, disabled by default.
+    ///
+    /// A missing key is always read as `None`.
+    pub fn empty_as_none(mut self, empty_as_none: bool) -> Self {
+        self.empty_as_none = empty_as_none;
+        self
+    }
+
+    /// Omit the map entries and struct fields which are `None` instead of writing `null`, disabled by default.
+    ///
+    /// This is what browsers do for unset inputs. `None` items of sequences are still written as `null`.
+    pub fn skip_none(mut self, skip_none: bool) -> Self {
+        self.skip_none = skip_none;
+        self
+    }
+}
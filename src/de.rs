@@ -1,26 +1,68 @@
-use std::io::Read;
+use std::{borrow::Cow, io::Read};
 
-use crate::parser::Map;
+use crate::{Config, ObjectStyle, SequenceLength, SequenceStyle, lexer::Span, parser::{IntoIter, Map, Notation}};
 
 pub use super::{Error, Result};
-use serde::{Deserialize, de::{DeserializeOwned, IntoDeserializer}};
+use serde::{Deserialize, de::{DeserializeOwned, Unexpected}};
 use super::parser::{Parser, Value};
 
-struct MapAccessor {
-    value: Option<Value>,
-    iter: std::collections::hash_map::IntoIter<String, Value>
+/// Attach the location of the value being decoded to the error, if known.
+fn locate(err: Error, span: Option<Span>) -> Error {
+    match span {
+        Some(span) => err.at(span),
+        None => err
+    }
+}
+
+/// Split a single value into the items of a delimited sequence, located at the whole value.
+fn split_delimited(value: Cow<'_, str>, span: Span, delimiter: char) -> Vec<Value<'_>> {
+    let notation = Notation::Assigned;
+    match value {
+        Cow::Borrowed("") => vec![],
+        Cow::Owned(value) if value.is_empty() => vec![],
+        Cow::Borrowed(value) => value.split(delimiter).map(|item| Value::Single(Cow::Borrowed(item), span, notation)).collect(),
+        Cow::Owned(value) => value.split(delimiter).map(|item| Value::Single(Cow::Owned(item.to_string()), span, notation)).collect(),
+    }
+}
+
+/// Parse a number written in the same form as it would be encoded.
+fn canonical<T: std::str::FromStr + ToString>(value: &str) -> Option<T> {
+    value.parse::<T>().ok().filter(|n| n.to_string() == value)
+}
+
+/// Parse a decimal literal (`2.50`, `-1.0`, `1e3`), but neither an integer nor `inf` or `NaN`.
+fn decimal(value: &str) -> Option<f64> {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let is_decimal = digits.starts_with(|ch: char| ch.is_ascii_digit())
+        && digits.contains(['.', 'e', 'E'])
+        && digits.chars().all(|ch| ch.is_ascii_digit() || matches!(ch, '.' | 'e' | 'E' | '+' | '-'));
+    is_decimal.then(|| value.parse::<f64>().ok()).flatten()
+}
+
+/// Whether the map is keyed by the indices `0` to `n - 1`, in any order.
+fn is_indexed(map: &Map<'_>) -> bool {
+    let mut indices = map.iter().map(|(k, _)| k.parse::<usize>().ok()).collect::<Option<Vec<_>>>().unwrap_or_default();
+    indices.sort_unstable();
+    !indices.is_empty() && indices.iter().enumerate().all(|(i, index)| i == *index)
+}
+
+struct MapAccessor<'de> {
+    value: Option<(Cow<'de, str>, Value<'de>)>,
+    iter: IntoIter<'de>,
+    config: Config
 }
 
-impl From<Map> for MapAccessor {
-    fn from(value: Map) -> Self {
+impl<'de> MapAccessor<'de> {
+    fn new(map: Map<'de>, config: Config) -> Self {
         Self {
             value: None,
-            iter: value.into_iter()
+            iter: map.into_iter(),
+            config
         }
     }
 }
 
-impl<'de> serde::de::MapAccess<'de> for MapAccessor {
+impl<'de> serde::de::MapAccess<'de> for MapAccessor<'de> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> std::result::Result<Option<K::Value>, Self::Error>
@@ -28,9 +70,10 @@ impl<'de> serde::de::MapAccess<'de> for MapAccessor {
         K: serde::de::DeserializeSeed<'de> {
         match self.iter.next() {
             Some((k, v)) => {
-                let key = seed.deserialize(FormDecoder(k.into()))?;
-                self.value = Some(v);
-                return Ok(Some(key))
+                let span = v.span();
+                let key = seed.deserialize(FormDecoder::new(k.clone().into(), self.config)).map_err(|err| locate(err, span))?;
+                self.value = Some((k, v));
+                Ok(Some(key))
             },
             None => Ok(None),
         }
@@ -40,96 +83,265 @@ impl<'de> serde::de::MapAccess<'de> for MapAccessor {
     where
         V: serde::de::DeserializeSeed<'de> {
         match std::mem::take(&mut self.value) {
-            Some(value) => {
-                seed.deserialize(FormDecoder(value))
+            Some((key, value)) => {
+                let span = value.span();
+                seed.deserialize(FormDecoder::new(value, self.config)).map_err(|err| locate(err, span).in_field(&key))
             },
             None => Err(Error::MissingMapValue),
         }
     }    
 }
 
-struct SeqAccessor {
+struct SeqAccessor<'de> {
     index: usize,
-    len: usize,
-    map: Map
+    /// The items of the sequence, or none for the missing ones.
+    items: std::vec::IntoIter<Option<Value<'de>>>,
+    config: Config
 }
 
-impl<'de> serde::de::SeqAccess<'de> for SeqAccessor {
+impl<'de> serde::de::SeqAccess<'de> for SeqAccessor<'de> {
     type Error = Error;
    
     fn next_element_seed<T>(&mut self, seed: T) -> std::result::Result<Option<T::Value>, Self::Error>
     where
         T: serde::de::DeserializeSeed<'de> {
-        if self.index >= self.len { return Ok(None) }
-        
-        let (_, v) = self.map.take(&self.index.to_string()).ok_or_else(|| Error::MissingSequenceItem)?;
+        let Some(item) = self.items.next() else { return Ok(None) };
+
+        let key = self.index.to_string();
+        let v = item.ok_or_else(|| Error::MissingSequenceItem.in_field(&key))?;
         self.index += 1;
         
-        let value = seed.deserialize(FormDecoder(v))?;
+        let span = v.span();
+        let value = seed.deserialize(FormDecoder::new(v, self.config)).map_err(|err| locate(err, span).in_field(&key))?;
         Ok(Some(value))
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
 }
 
-impl TryFrom<Map> for SeqAccessor {
-    type Error = Error;
+impl<'de> SeqAccessor<'de> {
+    fn new(items: Vec<Value<'de>>, config: Config) -> Self {
+        Self {
+            index: 0,
+            items: items.into_iter().map(Some).collect::<Vec<_>>().into_iter(),
+            config
+        }
+    }
 
-    fn try_from(map: Map) -> Result<Self> {
-        let len: usize = map.borrow(&["$length"])
-            .ok_or_else(|| Error::MissingSequenceLength)?
-            .try_as_ref_single()
-            .ok_or_else(|| Error::ExpectingString)?
-            .parse::<usize>()
-            .map_err(|_| Error::ExpectingUsize)?;
+    /// Read a sequence from a map keyed by the item indices, along with a length key unless it can be inferred.
+    fn try_from_map(mut map: Map<'de>, config: Config) -> Result<Self> {
+        let items = match (map.take_length(), config.sequence_length) {
+            (Some(len), _) => {
+                let len = len.try_as_ref_single()
+                    .ok_or(Error::ExpectingString)?
+                    .parse::<usize>()
+                    .map_err(|_| Error::ExpectingUsize)?;
+
+                // Each item has its own key, so the length cannot exceed the number of keys: this
+                // also prevents allocating for any length sent by a client.
+                if len > map.len() {
+                    let missing = (0..=map.len()).find(|i| map.get(&i.to_string()).is_none()).unwrap_or_default();
+                    return Err(Error::MissingSequenceItem.in_field(&missing.to_string()))
+                }
+
+                (0..len).map(|i| map.take(&i.to_string()).map(|(_, v)| v)).collect::<Vec<_>>()
+            },
+            (None, SequenceLength::Explicit) => return Err(Error::MissingSequenceLength),
+            (None, SequenceLength::Inferred { allow_gaps }) => return Self::try_from_indices(map, allow_gaps, config),
+        };
+
+        Ok(Self {
+            index: 0,
+            items: items.into_iter(),
+            config
+        })
+
+    }
+
+    /// Read a sequence from a map keyed by the item indices, its length being the last index plus one.
+    fn try_from_indices(map: Map<'de>, allow_gaps: bool, config: Config) -> Result<Self> {
+        let mut items = map.into_iter()
+            .map(|(k, v)| k.parse::<usize>().map(|i| (i, v)).map_err(|_| Error::ExpectingUsize.in_field(&k)))
+            .collect::<Result<Vec<_>>>()?;
+        items.sort_by_key(|(i, _)| *i);
+
+        // The indices are sent by the client: the first one which is not its position is missing,
+        // rather than allocating up to the last index.
+        if !allow_gaps && let Some(missing) = items.iter().enumerate().position(|(position, (i, _))| position != *i) {
+            return Err(Error::MissingSequenceItem.in_field(&missing.to_string()))
+        }
 
         Ok(Self {
             index: 0,
-            len,
-            map
+            items: items.into_iter().map(|(_, v)| Some(v)).collect::<Vec<_>>().into_iter(),
+            config
         })
+    }
+}
+
+/// An externally tagged enum, either a unit variant (`shape=circle`) or a variant with its content
+/// nested under its name (`shape.circle.radius=2`).
+struct EnumAccessor<'de> {
+    variant: Cow<'de, str>,
+    value: Option<Value<'de>>,
+    config: Config
+}
+
+impl<'de> serde::de::EnumAccess<'de> for EnumAccessor<'de> {
+    type Error = Error;
+    type Variant = VariantAccessor<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: serde::de::DeserializeSeed<'de> {
+        let variant = seed.deserialize(FormDecoder::new(self.variant.clone().into(), self.config))?;
+        Ok((variant, VariantAccessor { variant: self.variant, value: self.value, config: self.config }))
+    }
+}
+
+struct VariantAccessor<'de> {
+    variant: Cow<'de, str>,
+    value: Option<Value<'de>>,
+    config: Config
+}
 
+impl<'de> VariantAccessor<'de> {
+    /// Decode the content of the variant, or fail if it has none.
+    fn content(self, expected: &'static str) -> Result<(FormDecoder<'de>, Cow<'de, str>, Option<Span>)> {
+        match self.value {
+            Some(value) => {
+                let span = value.span();
+                Ok((FormDecoder::new(value, self.config), self.variant, span))
+            },
+            None => Err(serde::de::Error::invalid_type(Unexpected::UnitVariant, &expected)),
+        }
     }
 }
 
-pub struct FormDecoder(Value);
+impl<'de> serde::de::VariantAccess<'de> for VariantAccessor<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None => Ok(()),
+            Some(_) => Err(serde::de::Error::invalid_type(Unexpected::Map, &"unit variant")),
+        }
+    }
 
-impl FormDecoder {
-    fn new(input: &str) -> Result<Self> {
-        let parser = Parser::new(input);
-        let value = parser.collect::<Result<Value>>()?;
-        Ok(Self(value))
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: serde::de::DeserializeSeed<'de> {
+        let (decoder, variant, span) = self.content("newtype variant")?;
+        seed.deserialize(decoder).map_err(|err| locate(err, span).in_field(&variant))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de> {
+        let (decoder, variant, span) = self.content("tuple variant")?;
+        serde::Deserializer::deserialize_seq(decoder, visitor).map_err(|err| locate(err, span).in_field(&variant))
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: serde::de::Visitor<'de> {
+        let (decoder, variant, span) = self.content("struct variant")?;
+        serde::Deserializer::deserialize_map(decoder, visitor).map_err(|err| locate(err, span).in_field(&variant))
     }
 }
 
-impl FormDecoder {
-    fn try_as_single(self) -> Result<String> {
-        self.0.try_as_single().ok_or(Error::ExpectingString)
+pub struct FormDecoder<'de> {
+    value: Value<'de>,
+    config: Config,
+    /// Whether the value is read as-is into a [`crate::Value`].
+    raw: bool
+}
+
+impl<'de> FormDecoder<'de> {
+    fn new(value: Value<'de>, config: Config) -> Self {
+        Self { value, config, raw: false }
     }
 
-    fn try_as_map(self) -> Result<Map> {
-        self.0.try_as_map().ok_or(Error::ExpectingMap)
+    fn parse(input: &'de str, config: Config) -> Result<Self> {
+        let parser = Parser::new(input, config);
+        let value = Value::from_pairs(parser, config)?;
+        Ok(Self::new(value, config))
     }
 }
 
-impl<'de> serde::Deserializer<'de> for FormDecoder {
+impl<'de> FormDecoder<'de> {
+    fn try_as_single(self) -> Result<Cow<'de, str>> {
+        self.value.try_as_single().ok_or(Error::ExpectingString)
+    }
+
+    fn try_as_map(self) -> Result<Map<'de>> {
+        match self.value {
+            Value::Single(value, span, _) if let ObjectStyle::Delimited(delimiter) = self.config.object_style => {
+                let items = split_delimited(value, span, delimiter);
+                if !items.len().is_multiple_of(2) {
+                    return Err(Error::ExpectingMap)
+                }
+
+                let mut map = Map::default();
+                let mut items = items.into_iter();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    map.insert(key.try_as_single().unwrap_or_default(), value);
+                }
+                Ok(map)
+            },
+            value => value.try_as_map().ok_or(Error::ExpectingMap)
+        }
+    }
+
+    fn try_as_seq(self) -> Result<SeqAccessor<'de>> {
+        let config = self.config;
+        match self.value {
+            Value::Seq(values) => Ok(SeqAccessor::new(values, config)),
+            Value::Map(map) => SeqAccessor::try_from_map(map, config),
+            single @ Value::Single(..) if config.sequence_style == SequenceStyle::Repeated => {
+                Ok(SeqAccessor::new(vec![single], config))
+            },
+            Value::Single(value, span, _) if let SequenceStyle::Delimited(delimiter) = config.sequence_style => {
+                Ok(SeqAccessor::new(split_delimited(value, span, delimiter), config))
+            },
+            Value::Single(..) => Err(Error::ExpectingMap),
+        }
+    }
+}
+
+impl<'de> serde::Deserializer<'de> for FormDecoder<'de> {
     type Error = Error;
 
     fn deserialize_struct<V>(self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de> {
         
-        let map_access = MapAccessor::from(self.try_as_map()?);
+        let config = self.config;
+        let map_access = MapAccessor::new(self.try_as_map()?, config);
         visitor.visit_map(map_access)
     }
 
     fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de> {
-        let single = self.try_as_single()?;
-        visitor.visit_enum(single.into_deserializer())
+        let config = self.config;
+        match self.value {
+            Value::Single(variant, ..) => visitor.visit_enum(EnumAccessor { variant, value: None, config }),
+            Value::Map(map) => {
+                let mut entries = map.into_iter();
+                match (entries.next(), entries.next()) {
+                    (Some((variant, value)), None) => visitor.visit_enum(EnumAccessor { variant, value: Some(value), config }),
+                    _ => Err(Error::ExpectingEnum)
+                }
+            },
+            Value::Seq(_) => Err(Error::ExpectingEnum),
+        }
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value> where V: serde::de::Visitor<'de> {
-        visitor.visit_str(&self.try_as_single()?)
+        self.deserialize_str(visitor)
     }
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value> where V: serde::de::Visitor<'de> {
@@ -137,13 +349,40 @@ impl<'de> serde::Deserializer<'de> for FormDecoder {
     }
     
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value> where V: serde::de::Visitor<'de> {
-        match &self.0 {
-            Value::Single(_) => self.deserialize_str(visitor),
+        match &self.value {
+            // Quoted strings are told apart from the other single values of a `Value`.
+            Value::Single(value, _, Notation::Quoted) if self.raw => visitor.visit_bytes(value.as_bytes()),
+            Value::Single(..) if !self.config.infer_types || self.raw => self.deserialize_str(visitor),
+            Value::Single(_, _, Notation::Quoted) => self.deserialize_str(visitor),
+            Value::Single(value, ..) => match value.as_ref() {
+                "null" if self.config.null_as_none => visitor.visit_unit(),
+                "true" => visitor.visit_bool(true),
+                "false" => visitor.visit_bool(false),
+                // Integers are only inferred from their canonical form, so that `007` stays a string.
+                value if let Some(n) = canonical::<u64>(value) => visitor.visit_u64(n),
+                value if let Some(n) = canonical::<i64>(value) => visitor.visit_i64(n),
+                value if let Some(n) = decimal(value) => visitor.visit_f64(n),
+                _ => self.deserialize_str(visitor),
+            },
+            Value::Map(map) if map.length().is_some() => self.deserialize_seq(visitor),
+            Value::Map(map) if self.config.sequence_length != SequenceLength::Explicit
+                && map.iter().next().is_some()
+                && map.iter().all(|(k, _)| k.parse::<usize>().is_ok()) => self.deserialize_seq(visitor),
+            Value::Map(map) if self.config.infer_types && !self.raw && is_indexed(map) => {
+                let config = self.config;
+                visitor.visit_seq(SeqAccessor::try_from_indices(self.try_as_map()?, false, config)?)
+            },
             Value::Map(_) => self.deserialize_map(visitor),
+            Value::Seq(_) => self.deserialize_seq(visitor),
         }
     }
     
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value> where V: serde::de::Visitor<'de> {
+        // A key without `=` is a flag, while an empty value is not a bool.
+        if let Value::Single(_, _, Notation::Valueless) = self.value {
+            return visitor.visit_bool(true)
+        }
+
         match self.try_as_single()?.to_lowercase().as_str() {
             "false" => visitor.visit_bool(false),
             "true" => visitor.visit_bool(true),
@@ -234,38 +473,42 @@ impl<'de> serde::Deserializer<'de> for FormDecoder {
     }
     
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value> where V: serde::de::Visitor<'de> {
-        visitor.visit_char(self.try_as_single()?.chars().next().ok_or_else(|| Error::ExpectingChar)?)
+        visitor.visit_char(self.try_as_single()?.chars().next().ok_or(Error::ExpectingChar)?)
     }
     
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value> where V: serde::de::Visitor<'de> {
-        visitor.visit_str(&self.try_as_single()?)
+        match self.try_as_single()? {
+            Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+            Cow::Owned(value) => visitor.visit_string(value)
+        }
     }
     
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value> where V: serde::de::Visitor<'de> {
-        visitor.visit_string(self.try_as_single()?.to_string())
+        visitor.visit_string(self.try_as_single()?.into_owned())
     }
     
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de> {
-        let seq_access = SeqAccessor::try_from(self.try_as_map()?)?;
+        let seq_access = self.try_as_seq()?;
         visitor.visit_seq(seq_access)
     }
     
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de> {
-        let seq_access = SeqAccessor::try_from(self.try_as_map()?)?;
+        let seq_access = self.try_as_seq()?;
         visitor.visit_seq(seq_access)
     }
     
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de> {
-        match &self.0 {
-            Value::Single(s) => {
-                match s.as_str() {
-                    "null" => visitor.visit_none(),
+        match &self.value {
+            Value::Single(s, ..) => {
+                match s.as_ref() {
+                    "null" if self.config.null_as_none => visitor.visit_none(),
+                    "" if self.config.empty_as_none => visitor.visit_none(),
                     _ => visitor.visit_some(self)
                 }
             },
@@ -285,68 +528,106 @@ impl<'de> serde::Deserializer<'de> for FormDecoder {
         self.deserialize_unit(visitor)
     }
     
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de> {
-        visitor.visit_newtype_struct(self)
+        match name {
+            crate::value::RAW => visitor.visit_newtype_struct(Self { raw: true, ..self }),
+            _ => visitor.visit_newtype_struct(self)
+        }
     }
     
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value> where V: serde::de::Visitor<'de> {
-        let seq_access = SeqAccessor::try_from(self.try_as_map()?)?;
+        let seq_access = self.try_as_seq()?;
         visitor.visit_seq(seq_access)
     }
     
     fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de> {
-        let seq_access = SeqAccessor::try_from(self.try_as_map()?)?;
+        let seq_access = self.try_as_seq()?;
         visitor.visit_seq(seq_access)
     }
     
     fn deserialize_tuple_struct<V>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de> {
-        let seq_access = SeqAccessor::try_from(self.try_as_map()?)?;
+        let seq_access = self.try_as_seq()?;
         visitor.visit_seq(seq_access)
     }
     
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de> {
-        let map_access = MapAccessor::from(self.try_as_map()?);
+        let config = self.config;
+        let map_access = MapAccessor::new(self.try_as_map()?, config);
         visitor.visit_map(map_access)
     }
 }
 
 
+impl Config {
+    /// Deserialize a value from a string slice with this configuration.
+    pub fn from_str<'de, T: Deserialize<'de>>(&self, input: &'de str) -> Result<T> {
+        FormDecoder::parse(input, *self)
+            .and_then(T::deserialize)
+            .map_err(|err| err.with_input(input))
+    }
+
+    /// Deserialize a value from a byte slice with this configuration.
+    /// 
+    /// The byte sequence is expected to be an UTF8 encoded string.
+    pub fn from_bytes<'de, T: Deserialize<'de>>(&self, bytes: &'de [u8]) -> Result<T> {
+        let s = std::str::from_utf8(bytes).map_err(|_| Error::ExpectingUtf8String)?;
+        self.from_str(s)
+    }
+
+    /// Deserialize a value from a byte stream with this configuration.
+    /// 
+    /// The byte sequence is expected to be an UTF8 encoded string.
+    pub fn from_reader<T: DeserializeOwned, Reader: Read>(&self, reader: &mut Reader) -> Result<T> {
+        let mut bytes: Vec<u8> = Default::default();
+        reader.read_to_end(&mut bytes).map_err(|err| Error::IoError(err.to_string()))?;
+        self.from_bytes(bytes.as_slice())
+    }
+
+    /// Deserialize a value from a [`Value`](crate::Value) tree with this configuration.
+    pub fn from_value<T: DeserializeOwned>(&self, value: crate::Value) -> Result<T> {
+        T::deserialize(FormDecoder::new(value.into(), *self))
+    }
+}
+
+/// Deserialize a value from a [`Value`](crate::Value) tree, without going through text.
+pub fn from_value<T: DeserializeOwned>(value: crate::Value) -> Result<T> {
+    Config::default().from_value(value)
+}
+
 /// Deserialize a value from a string slice.
 pub fn from_str<'de, T: Deserialize<'de>>(input: &'de str) -> Result<T> {
-    let deser = FormDecoder::new(input)?;
-    T::deserialize(deser)
+    Config::default().from_str(input)
 }
 
 /// Desrialize a value from a byte slice.
 /// 
 /// The byte sequence is expected to be an UTF8 encoded string.
 pub fn from_bytes<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T> {
-    let s = std::str::from_utf8(bytes).map_err(|_| Error::ExpectingUtf8String)?;
-    from_str(s)
+    Config::default().from_bytes(bytes)
 }
 
 /// Desrialize a value from a byte stream.
 /// 
 /// The byte sequence is expected to be an UTF8 encoded string.
 pub fn from_reader<T: DeserializeOwned, Reader: Read>(reader: &mut Reader) -> Result<T> {
-    let mut bytes: Vec<u8> = Default::default();
-    reader.read_to_end(&mut bytes).unwrap();
-    from_bytes(bytes.as_slice())
+    Config::default().from_reader(reader)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::io::Cursor;
+    use std::{borrow::Cow, collections::HashMap, io::Cursor};
 
-    use crate::{from_bytes, from_reader, from_str, tests::{ENCODED, Foo, fixture}};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Config, Error, KeySyntax, Map, ParameterStyle, SequenceLength, SequenceStyle, StringStyle, Value, from_bytes, from_reader, from_str, from_value, to_string, to_value, tests::{ENCODED, ENCODED_QUOTED, Foo, Nested, fixture}};
 
     #[test]
     fn test_deserialize_str() {
@@ -371,4 +652,883 @@ mod tests {
         let got = from_reader::<Foo, _>(&mut cursor).unwrap();
         assert_eq!(got, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_deserialize_empty_value() {
+        let expected = Nested { arg0: String::default(), arg1: 1.0 };
+        let encoded = to_string(&expected).unwrap();
+        assert_eq!(encoded, "arg0=&arg1=1");
+        assert_eq!(from_str::<Nested>(&encoded).unwrap(), expected);
+        assert_eq!(from_str::<Nested>("arg1=1&arg0=").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_deserialize_percent_encoded() {
+        let got = from_str::<Nested>("arg0=J%C3%A9r%C3%B4me%20%26%20co&ar%67%31=1.5").unwrap();
+        assert_eq!(got, Nested { arg0: "Jérôme & co".to_string(), arg1: 1.5 });
+
+        let expected = Nested { arg0: "100% \"sure\" = a&b".to_string(), arg1: 2.0 };
+        let got = from_str::<Nested>(&to_string(&expected).unwrap()).unwrap();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_deserialize_quoted() {
+        let config = Config::new().string_style(StringStyle::Quoted);
+        let got = config.from_str::<Foo>(ENCODED_QUOTED).unwrap();
+        assert_eq!(got, fixture());
+
+        let got = config.from_str::<Nested>("arg0=\"a&b=\\\"c\\\"\"&arg1=1.5").unwrap();
+        assert_eq!(got, Nested { arg0: "a&b=\"c\"".to_string(), arg1: 1.5 });
+    }
+
+    #[test]
+    fn test_deserialize_malformed() {
+        for input in ["a&b=1", "=x", "a==b", "arg0"] {
+            assert!(from_str::<Nested>(input).is_err(), "{input}");
+        }
+        assert!(Config::new().string_style(StringStyle::Quoted).from_str::<Nested>("arg0=\"abc").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_error_location() {
+        let err = from_str::<Foo>(&ENCODED.replace("arg5=1.01", "arg5=abc")).unwrap_err();
+        assert!(matches!(err.kind(), Error::ExpectingF32));
+        assert_eq!(err.offset(), Some(46));
+        assert_eq!(err.location().unwrap().fragment(), Some("arg5=abc"));
+        assert_eq!(err.to_string(), "arg5: expecting f32 at offset 46\n    arg5=abc\n         ^^^");
+
+        let err = from_str::<Nested>("arg0=a&arg1=1&&").unwrap_err();
+        assert!(matches!(err.kind(), Error::UnexpectedToken { offset: 14, .. }));
+        assert_eq!(err.to_string(), "expecting a key, found `&` at offset 14\n    &\n    ^");
+    }
+
+    #[test]
+    fn test_deserialize_error_path() {
+        let err = from_str::<Foo>(&ENCODED.replace("arg9.1.arg1=10.5", "arg9.1.arg1=abc")).unwrap_err();
+        assert!(matches!(err.kind(), Error::ExpectingF32));
+        assert_eq!(err.path(), Some("arg9.1.arg1"));
+        assert!(err.to_string().starts_with("arg9.1.arg1: expecting f32 at offset"));
+
+        let err = from_str::<Foo>(&ENCODED.replace("arg9.1.arg0=item1&", "")).unwrap_err();
+        assert_eq!(err.path(), Some("arg9.1"));
+
+        let err = from_str::<Foo>(&ENCODED.replace("arg9.%24length=2", "arg9.%24length=3")).unwrap_err();
+        assert!(matches!(err.kind(), Error::MissingSequenceItem));
+        assert_eq!(err.path(), Some("arg9.2"));
+    }
+
+    #[test]
+    fn test_deserialize_oversized_length() {
+        let err = from_str::<HashMap<String, Vec<u8>>>("a.0=1&a.%24length=99999999999999").unwrap_err();
+        assert!(matches!(err.kind(), Error::MissingSequenceItem));
+        assert_eq!(err.path(), Some("a.1"));
+
+        let err = from_str::<HashMap<String, Vec<u8>>>("a.1=1&a.%24length=3").unwrap_err();
+        assert_eq!(err.path(), Some("a.0"));
+    }
+
+    #[test]
+    fn test_deserialize_oversized_index() {
+        let inferred = Config::new().sequence_length(SequenceLength::Inferred { allow_gaps: false });
+        for input in ["a.18446744073709551615=1", "a.99999999999=1", "a.0=1&a.99999999999=1"] {
+            let err = inferred.from_str::<HashMap<String, Vec<u8>>>(input).unwrap_err();
+            assert!(matches!(err.kind(), Error::MissingSequenceItem), "{input}");
+        }
+
+        let gaps = Config::new().sequence_length(SequenceLength::Inferred { allow_gaps: true });
+        let got = gaps.from_str::<HashMap<String, Vec<u8>>>("a.18446744073709551615=2&a.3=1").unwrap();
+        assert_eq!(got["a"], vec![1, 2]);
+
+        // Maps are not read as sequences by inference unless their keys are contiguous indices.
+        let got = from_str::<Any>("a.18446744073709551615=1").unwrap();
+        assert_eq!(got, Any::Map(vec![("a".to_string(), Any::Map(vec![("18446744073709551615".to_string(), Any::U64(1))]))]));
+    }
+
+    #[test]
+    fn test_deserialize_lenient() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Query {
+            page: u32,
+            q: String,
+            debug: bool,
+            verbose: bool
+        }
+
+        let config = Config::new().lenient(true);
+        let got = config.from_str::<Query>("?page=2&&q=&debug&verbose=false&").unwrap();
+        assert_eq!(got, Query { page: 2, q: String::default(), debug: true, verbose: false });
+        assert!(from_str::<Query>("?page=2&&q=&debug&verbose=false&").is_err());
+
+        // Only a key without `=` is a flag, an empty value is not a bool.
+        let err = config.from_str::<Query>("page=2&q=&debug=&verbose=false").unwrap_err();
+        assert!(matches!(err.kind(), Error::ExpectingBool));
+    }
+
+    #[test]
+    fn test_deserialize_borrowed() {
+        #[derive(Debug, Deserialize)]
+        struct Borrowed<'a> {
+            name: &'a str,
+            #[serde(borrow)]
+            value: Cow<'a, str>,
+            nested: Nested
+        }
+
+        let input = String::from("name=abc&value=d+e&nested.arg0=f&nested.arg1=1");
+        let got = from_str::<Borrowed>(&input).unwrap();
+        assert_eq!(got.name, "abc");
+        assert!(std::ptr::eq(got.name.as_ptr(), input[5..].as_ptr()));
+        assert!(matches!(got.value, Cow::Owned(ref value) if value == "d e"));
+        assert_eq!(got.nested.arg0, "f");
+
+        // A value which needs decoding cannot be borrowed from the input.
+        assert!(from_str::<Borrowed>("name=a%20b&value=c&nested.arg0=f&nested.arg1=1").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_map_order() {
+        struct Ordered(Vec<(String, u32)>);
+
+        impl<'de> Deserialize<'de> for Ordered {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct OrderedVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for OrderedVisitor {
+                    type Value = Ordered;
+
+                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        formatter.write_str("a map")
+                    }
+
+                    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Ordered, A::Error> {
+                        let mut entries = Vec::new();
+                        while let Some(entry) = map.next_entry()? {
+                            entries.push(entry);
+                        }
+                        Ok(Ordered(entries))
+                    }
+                }
+
+                deserializer.deserialize_map(OrderedVisitor)
+            }
+        }
+
+        let got = from_str::<Ordered>("zeta=1&alpha=2&mu=3&beta=4").unwrap();
+        let keys = got.0.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["zeta", "alpha", "mu", "beta"]);
+    }
+
+    #[test]
+    fn test_deserialize_plus_as_space() {
+        let got = from_str::<Nested>("arg0=J%C3%A9r%C3%B4me+%2B+co&arg1=1.5").unwrap();
+        assert_eq!(got, Nested { arg0: "Jérôme + co".to_string(), arg1: 1.5 });
+    }
+
+    #[test]
+    fn test_deserialize_from_value() {
+        let mut value = to_value(&fixture()).unwrap();
+        value["arg9.1.arg0"] = Value::from("changed");
+
+        let mut expected = fixture();
+        expected.arg9[1].arg0 = "changed".to_string();
+        assert_eq!(from_value::<Foo>(value).unwrap(), expected);
+
+        let err = from_value::<Nested>(Value::from("abc")).unwrap_err();
+        assert!(matches!(err.kind(), Error::ExpectingMap));
+        assert!(err.location().is_none());
+    }
+
+    #[test]
+    fn test_deserialize_repeated_keys() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Filter {
+            tag: Vec<String>,
+            single: Vec<u8>,
+            items: Vec<Nested>
+        }
+
+        let config = Config::new().sequence_style(SequenceStyle::Repeated);
+        let got = config.from_str::<Filter>("tag=a&single=1&tag=b&items.0.arg0=x&items.0.arg1=1.5&items.%24length=1&tag=c").unwrap();
+        assert_eq!(got, Filter {
+            tag: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            single: vec![1],
+            items: vec![Nested { arg0: "x".to_string(), arg1: 1.5 }]
+        });
+
+        let err = config.from_str::<Nested>("arg0=a&arg0=b&arg1=1").unwrap_err();
+        assert!(matches!(err.kind(), Error::ExpectingString));
+        assert_eq!(err.path(), Some("arg0"));
+    }
+
+    #[test]
+    fn test_deserialize_bracket_keys() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct User {
+            name: String,
+            tags: Vec<String>,
+            address: Nested,
+            items: Vec<Nested>
+        }
+
+        let config = Config::new().key_syntax(KeySyntax::Bracket);
+        let input = "name=Jane&tags[]=a&address[arg0]=x&address[arg1]=1&tags%5B%5D=b\
+            &items[0][arg0]=y&items[0][arg1]=2&items[$length]=1";
+        let got = config.from_str::<User>(input).unwrap();
+        assert_eq!(got, User {
+            name: "Jane".to_string(),
+            tags: vec!["a".to_string(), "b".to_string()],
+            address: Nested { arg0: "x".to_string(), arg1: 1.0 },
+            items: vec![Nested { arg0: "y".to_string(), arg1: 2.0 }]
+        });
+
+        let dotted = from_str::<Value>("user.name=Jane&user.tags.0=a&user.tags.%24length=1").unwrap();
+        let bracket = config.from_str::<Value>("user[name]=Jane&user[tags][]=a").unwrap();
+        assert_eq!(dotted, bracket);
+    }
+
+    #[test]
+    fn test_deserialize_inferred_length() {
+        let input = "arg9.1.arg0=item1&arg9.1.arg1=10.5&arg9.0.arg0=item0&arg9.0.arg1=20.5";
+        let expected = vec![
+            Nested { arg0: "item0".to_string(), arg1: 20.5 },
+            Nested { arg0: "item1".to_string(), arg1: 10.5 },
+        ];
+
+        let config = Config::new().sequence_length(SequenceLength::Inferred { allow_gaps: false });
+        let got = config.from_str::<HashMap<String, Vec<Nested>>>(input).unwrap();
+        assert_eq!(got["arg9"], expected);
+        assert_eq!(config.from_str::<Foo>(ENCODED).unwrap(), fixture());
+        assert!(from_str::<HashMap<String, Vec<Nested>>>(input).is_err());
+
+        let err = config.from_str::<HashMap<String, Vec<u8>>>("a.0=1&a.2=3").unwrap_err();
+        assert!(matches!(err.kind(), Error::MissingSequenceItem));
+        assert_eq!(err.path(), Some("a.1"));
+
+        let err = config.from_str::<HashMap<String, Vec<u8>>>("a.0=1&a.b=3").unwrap_err();
+        assert!(matches!(err.kind(), Error::ExpectingUsize));
+        assert_eq!(err.path(), Some("a.b"));
+
+        let config = Config::new().sequence_length(SequenceLength::Inferred { allow_gaps: true });
+        let got = config.from_str::<HashMap<String, Vec<u8>>>("a.5=3&a.0=1&a.2=2").unwrap();
+        assert_eq!(got["a"], vec![1, 2, 3]);
+
+        let got = config.from_str::<Value>("a.0=1&a.1=2").unwrap();
+        assert_eq!(got["a"], Value::Seq(vec![Value::from("1"), Value::from("2")]));
+    }
+
+    #[test]
+    fn test_deserialize_separator() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Legacy {
+            #[serde(rename = "user.name")]
+            name: String,
+            items: Vec<Nested>
+        }
+
+        let config = Config::new().separator("__").length_key("count");
+        let got = config.from_str::<Legacy>("user.name=Jane&items__0__arg0=a&items__0__arg1=1&items__count=1").unwrap();
+        assert_eq!(got, Legacy { name: "Jane".to_string(), items: vec![Nested { arg0: "a".to_string(), arg1: 1.0 }] });
+    }
+
+    #[test]
+    fn test_deserialize_escaped_keys() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Reserved {
+            #[serde(rename = "$length")]
+            length: u8,
+            map: HashMap<String, String>,
+            items: Vec<u8>
+        }
+
+        let expected = Reserved {
+            length: 3,
+            map: HashMap::from([
+                ("a.b".to_string(), "1".to_string()),
+                ("c=d&e".to_string(), "2".to_string()),
+                ("$length".to_string(), "3".to_string()),
+                ("back\\slash".to_string(), "4".to_string()),
+            ]),
+            items: vec![5]
+        };
+
+        let encoded = to_string(&expected).unwrap();
+        assert!(encoded.contains("map.a%5C.b=1"));
+        assert!(encoded.contains("map.%5C%24length=3"));
+        assert_eq!(from_str::<Reserved>(&encoded).unwrap(), expected);
+
+        let config = Config::new().key_syntax(KeySyntax::Bracket);
+        let encoded = config.to_string(&HashMap::from([("a[0]", "1")])).unwrap();
+        assert_eq!(encoded, "a%5C%5B0%5C%5D=1");
+        assert_eq!(config.from_str::<HashMap<String, String>>(&encoded).unwrap(), HashMap::from([("a[0]".to_string(), "1".to_string())]));
+    }
+
+    #[test]
+    fn test_deserialize_delimited() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Query {
+            ids: Vec<u32>,
+            empty: Vec<u32>,
+            items: Vec<Nested>
+        }
+
+        let config = Config::new().sequence_style(SequenceStyle::Delimited(','));
+        let got = config.from_str::<Query>("ids=1%2C2,3&empty=&items.0.arg0=a&items.0.arg1=1&items.%24length=1").unwrap();
+        assert_eq!(got, Query { ids: vec![1, 2, 3], empty: vec![], items: vec![Nested { arg0: "a".to_string(), arg1: 1.0 }] });
+
+        let err = config.from_str::<Query>("ids=1,x&empty=&items.%24length=0").unwrap_err();
+        assert!(matches!(err.kind(), Error::ExpectingU32));
+        assert_eq!(err.path(), Some("ids.1"));
+        assert_eq!(err.offset(), Some(4));
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Ids {
+            #[serde(with = "crate::delimited::comma")]
+            ids: Vec<u32>
+        }
+
+        for (ids, encoded) in [(vec![1, 2, 3], "ids=1%2C2%2C3"), (vec![], "ids=")] {
+            let value = Ids { ids };
+            assert_eq!(to_string(&value).unwrap(), encoded);
+            assert_eq!(from_str::<Ids>(encoded).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_parameter_styles() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        #[serde(rename_all = "UPPERCASE")]
+        struct Color {
+            r: u8,
+            g: u8,
+            b: u8
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Colors {
+            color: Vec<String>
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Rgb {
+            color: Color
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct FlattenedRgb {
+            #[serde(flatten)]
+            color: Color
+        }
+
+        // The examples of the OpenAPI specification.
+        let colors = Colors { color: vec!["blue".to_string(), "black".to_string(), "brown".to_string()] };
+        let rgb = Rgb { color: Color { r: 100, g: 200, b: 150 } };
+        let cases = [
+            (ParameterStyle::Form, false, Some("color=blue,black,brown"), "color=R,100,G,200,B,150"),
+            (ParameterStyle::Form, true, Some("color=blue&color=black&color=brown"), "R=100&G=200&B=150"),
+            (ParameterStyle::SpaceDelimited, false, Some("color=blue%20black%20brown"), "color=R%20100%20G%20200%20B%20150"),
+            (ParameterStyle::PipeDelimited, false, Some("color=blue|black|brown"), "color=R|100|G|200|B|150"),
+            (ParameterStyle::DeepObject, true, None, "color%5BR%5D=100&color%5BG%5D=200&color%5BB%5D=150"),
+        ];
+
+        for (style, explode, encoded_colors, encoded_rgb) in cases {
+            let config = Config::new().parameter_style(style, explode);
+            if let Some(encoded) = encoded_colors {
+                assert_eq!(config.to_string(&colors).unwrap(), encoded, "{style:?} {explode}");
+                assert_eq!(config.from_str::<Colors>(encoded).unwrap(), colors, "{style:?} {explode}");
+            }
+
+            assert_eq!(config.to_string(&rgb).unwrap(), encoded_rgb, "{style:?} {explode}");
+            match (style, explode) {
+                (ParameterStyle::Form, true) => assert_eq!(config.from_str::<FlattenedRgb>(encoded_rgb).unwrap().color, rgb.color),
+                _ => assert_eq!(config.from_str::<Rgb>(encoded_rgb).unwrap(), rgb, "{style:?} {explode}"),
+            }
+        }
+
+        let config = Config::new().parameter_style(ParameterStyle::DeepObject, true);
+        assert_eq!(config.from_str::<Rgb>("color[R]=100&color[G]=200&color[B]=150").unwrap(), rgb);
+
+        let config = Config::new().parameter_style(ParameterStyle::Form, false);
+        assert!(matches!(config.from_str::<Rgb>("color=R,100,G").unwrap_err().kind(), Error::ExpectingMap));
+
+        // The sequences of flattened structs are read through a helper.
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Filter {
+            #[serde(with = "crate::repeated")]
+            tags: Vec<String>,
+            min: f64
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Search {
+            page: u32,
+            #[serde(flatten)]
+            filter: Filter
+        }
+
+        let config = Config::new().parameter_style(ParameterStyle::Form, true);
+        for (tags, encoded) in [(vec!["red"], "page=1&tags=red&min=2.5"), (vec!["red", "blue"], "page=1&tags=red&tags=blue&min=2.5")] {
+            let search = Search { page: 1, filter: Filter { tags: tags.into_iter().map(String::from).collect(), min: 2.5 } };
+            assert_eq!(config.to_string(&search).unwrap(), encoded);
+            assert_eq!(config.from_str::<Search>(encoded).unwrap(), search);
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Ids {
+            #[serde(with = "crate::delimited::comma")]
+            ids: Vec<u32>
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Page {
+            page: u32,
+            #[serde(flatten)]
+            ids: Ids
+        }
+
+        let config = Config::new().parameter_style(ParameterStyle::Form, false);
+        let page = Page { page: 1, ids: Ids { ids: vec![1, 2, 3] } };
+        assert_eq!(config.to_string(&page).unwrap(), "page=1&ids=1%2C2%2C3");
+        assert_eq!(config.from_str::<Page>("page=1&ids=1%2C2%2C3").unwrap(), page);
+        assert_eq!(config.from_str::<Page>("page=1&ids=1,2,3").unwrap(), page);
+    }
+
+    #[test]
+    fn test_deserialize_enums() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Shape {
+            Empty,
+            Square(f32),
+            Point(i32, i32),
+            Circle { radius: f32 }
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Drawing {
+            shape: Shape,
+            layers: Vec<Shape>
+        }
+
+        let cases = [
+            (Shape::Empty, "shape=empty"),
+            (Shape::Square(1.5), "shape.square=1.5"),
+            (Shape::Point(1, -2), "shape.point.0=1&shape.point.1=-2&shape.point.%24length=2"),
+            (Shape::Circle { radius: 2.0 }, "shape.circle.radius=2"),
+        ];
+
+        for (shape, encoded) in cases {
+            let drawing = Drawing { shape, layers: vec![Shape::Empty, Shape::Circle { radius: 1.0 }] };
+            let got = to_string(&drawing).unwrap();
+            assert!(got.starts_with(&format!("{encoded}&layers.0=empty&layers.1.circle.radius=1&")), "{got}");
+            assert_eq!(from_str::<Drawing>(&got).unwrap(), drawing);
+        }
+
+        let err = from_str::<Drawing>("shape.circle.radius=abc&layers.%24length=0").unwrap_err();
+        assert!(matches!(err.kind(), Error::ExpectingF32));
+        assert_eq!(err.path(), Some("shape.circle.radius"));
+
+        for input in ["shape=hexagon", "shape.empty=1", "shape=circle", "shape.circle.radius=1&shape.square=1"] {
+            assert!(from_str::<Drawing>(&format!("{input}&layers.%24length=0")).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_deserialize_tagged_enums() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        #[serde(tag = "type", rename_all = "lowercase")]
+        enum Event {
+            Created {
+                id: u32,
+                #[serde(with = "crate::string")]
+                name: String,
+                price: f64
+            },
+            Deleted { id: u32, soft: bool },
+            Renamed { id: u32, name: String },
+            Tagged {
+                #[serde(with = "crate::delimited::comma")]
+                ids: Vec<u32>
+            },
+            Ping
+        }
+
+        let cases = [
+            (Event::Created { id: 42, name: "42".to_string(), price: 9.5 }, "type=created&id=42&name=42&price=9.5"),
+            (Event::Created { id: 1, name: "true".to_string(), price: 0.5 }, "type=created&id=1&name=true&price=0.5"),
+            (Event::Deleted { id: 7, soft: true }, "type=deleted&id=7&soft=true"),
+            (Event::Renamed { id: 3, name: "007".to_string() }, "type=renamed&id=3&name=007"),
+            (Event::Tagged { ids: vec![5] }, "type=tagged&ids=5"),
+            (Event::Tagged { ids: vec![1, 2] }, "type=tagged&ids=1%2C2"),
+            (Event::Ping, "type=ping"),
+        ];
+
+        for (event, encoded) in cases {
+            assert_eq!(to_string(&event).unwrap(), encoded);
+            assert_eq!(from_str::<Event>(encoded).unwrap(), event);
+        }
+
+        assert_eq!(from_str::<Event>("id=7&soft=false&type=deleted").unwrap(), Event::Deleted { id: 7, soft: false });
+        assert_eq!(
+            from_str::<Event>("type=created&id=1&name=a&price=9.50").unwrap(),
+            Event::Created { id: 1, name: "a".to_string(), price: 9.5 }
+        );
+        assert!(from_str::<Event>("type=updated&id=7").is_err());
+
+        // A plain `String` cannot be read from an inferred number.
+        let err = from_str::<Event>("type=renamed&id=3&name=42").unwrap_err();
+        assert!(err.to_string().contains("expected a string"), "{err}");
+    }
+
+    #[test]
+    fn test_deserialize_adjacently_tagged_enums() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        #[serde(tag = "t", content = "c", rename_all = "lowercase")]
+        enum Event {
+            Created { id: u32, tags: Vec<String> },
+            Deleted(u32),
+            Ping
+        }
+
+        let cases = [
+            (Event::Created { id: 1, tags: vec!["a".to_string()] }, "t=created&c.id=1&c.tags.0=a&c.tags.%24length=1"),
+            (Event::Deleted(7), "t=deleted&c=7"),
+            (Event::Ping, "t=ping"),
+        ];
+
+        for (event, encoded) in cases {
+            assert_eq!(to_string(&event).unwrap(), encoded);
+            assert_eq!(from_str::<Event>(encoded).unwrap(), event);
+        }
+
+        // The content is buffered when it comes before the tag.
+        assert_eq!(from_str::<Event>("c=7&t=deleted").unwrap(), Event::Deleted(7));
+        assert_eq!(
+            from_str::<Event>("c.id=1&c.tags.0=a&c.tags.%24length=1&t=created").unwrap(),
+            Event::Created { id: 1, tags: vec!["a".to_string()] }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_untagged_enums() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        #[serde(untagged)]
+        enum Id {
+            Number(u64),
+            Signed(i64),
+            Flag(bool),
+            Name(String)
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        #[serde(untagged)]
+        enum Target {
+            User { user: Id, admin: bool },
+            Group { group: Id },
+            Address { zip: String },
+            Label {
+                #[serde(with = "crate::string")]
+                label: String
+            }
+        }
+
+        let cases = [
+            (Target::User { user: Id::Number(42), admin: false }, "user=42&admin=false"),
+            (Target::User { user: Id::Signed(-1), admin: true }, "user=-1&admin=true"),
+            (Target::Group { group: Id::Flag(true) }, "group=true"),
+            (Target::Group { group: Id::Name("0042".to_string()) }, "group=0042"),
+            (Target::Address { zip: "SW1A".to_string() }, "zip=SW1A"),
+            (Target::Label { label: "42".to_string() }, "label=42"),
+            (Target::Label { label: "true".to_string() }, "label=true"),
+            (Target::Group { group: Id::Name("staff".to_string()) }, "group=staff"),
+        ];
+
+        for (target, encoded) in cases {
+            assert_eq!(to_string(&target).unwrap(), encoded);
+            assert_eq!(from_str::<Target>(encoded).unwrap(), target);
+        }
+
+        assert!(from_str::<Target>("admin=true").is_err());
+
+        // A plain `String` cannot be read from an inferred number.
+        let err = from_str::<Target>("zip=75001").unwrap_err();
+        assert!(err.to_string().contains("data did not match any variant"), "{err}");
+    }
+
+    /// A self-describing value, recording which `visit_*` method was called.
+    #[derive(Debug, PartialEq)]
+    enum Any {
+        Unit,
+        Bool(bool),
+        U64(u64),
+        I64(i64),
+        F64(f64),
+        Str(String),
+        Seq(Vec<Any>),
+        Map(Vec<(String, Any)>)
+    }
+
+    impl<'de> Deserialize<'de> for Any {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+            struct AnyVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for AnyVisitor {
+                type Value = Any;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("any value")
+                }
+
+                fn visit_unit<E>(self) -> std::result::Result<Any, E> { Ok(Any::Unit) }
+                fn visit_bool<E>(self, v: bool) -> std::result::Result<Any, E> { Ok(Any::Bool(v)) }
+                fn visit_u64<E>(self, v: u64) -> std::result::Result<Any, E> { Ok(Any::U64(v)) }
+                fn visit_i64<E>(self, v: i64) -> std::result::Result<Any, E> { Ok(Any::I64(v)) }
+                fn visit_f64<E>(self, v: f64) -> std::result::Result<Any, E> { Ok(Any::F64(v)) }
+                fn visit_str<E>(self, v: &str) -> std::result::Result<Any, E> { Ok(Any::Str(v.to_string())) }
+
+                fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Any, A::Error> {
+                    let mut items = Vec::default();
+                    while let Some(item) = seq.next_element()? {
+                        items.push(item);
+                    }
+                    Ok(Any::Seq(items))
+                }
+
+                fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> std::result::Result<Any, A::Error> {
+                    let mut entries = Vec::default();
+                    while let Some(entry) = map.next_entry()? {
+                        entries.push(entry);
+                    }
+                    Ok(Any::Map(entries))
+                }
+            }
+
+            deserializer.deserialize_any(AnyVisitor)
+        }
+    }
+
+    #[test]
+    fn test_deserialize_any_inference() {
+        let input = "a=true&b=null&c=42&d=-1&e=1.5&f=007&g=1e3&h=x&i.0=p&i.1=q&j.1=y&j.0=x&k.0=x&k.2=z&l.0=x&l.%24length=1&m=2.50&n=-1.0&o=1.&p=.5&q=inf";
+        let got = from_str::<Any>(input).unwrap();
+        let str = |s: &str| Any::Str(s.to_string());
+        let expected = Any::Map(vec![
+            ("a".to_string(), Any::Bool(true)),
+            ("b".to_string(), Any::Unit),
+            ("c".to_string(), Any::U64(42)),
+            ("d".to_string(), Any::I64(-1)),
+            ("e".to_string(), Any::F64(1.5)),
+            ("f".to_string(), str("007")),
+            ("g".to_string(), Any::F64(1000.0)),
+            ("h".to_string(), str("x")),
+            ("i".to_string(), Any::Seq(vec![str("p"), str("q")])),
+            ("j".to_string(), Any::Seq(vec![str("x"), str("y")])),
+            ("k".to_string(), Any::Map(vec![("0".to_string(), str("x")), ("2".to_string(), str("z"))])),
+            ("l".to_string(), Any::Seq(vec![str("x")])),
+            ("m".to_string(), Any::F64(2.5)),
+            ("n".to_string(), Any::F64(-1.0)),
+            ("o".to_string(), Any::F64(1.0)),
+            ("p".to_string(), str(".5")),
+            ("q".to_string(), str("inf")),
+        ]);
+        assert_eq!(got, expected);
+
+        // Quoted values are strings.
+        let got = Config::new().string_style(StringStyle::Quoted).from_str::<Any>("a=\"42\"&b=42").unwrap();
+        assert_eq!(got, Any::Map(vec![("a".to_string(), str("42")), ("b".to_string(), Any::U64(42))]));
+
+        let got = Config::new().infer_types(false).from_str::<Any>("a=true&b=null&c=42&i.0=p&l.0=x&l.%24length=1").unwrap();
+        let expected = Any::Map(vec![
+            ("a".to_string(), str("true")),
+            ("b".to_string(), str("null")),
+            ("c".to_string(), str("42")),
+            ("i".to_string(), Any::Map(vec![("0".to_string(), str("p"))])),
+            ("l".to_string(), Any::Seq(vec![str("x")])),
+        ]);
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_deserialize_flatten() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Pagination {
+            page: u32,
+            per_page: u8
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Range {
+            min_price: f64,
+            max_price: Option<f64>
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Filter {
+            active: bool,
+            tags: Vec<String>,
+            #[serde(flatten)]
+            range: Range
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Query {
+            q: String,
+            #[serde(flatten)]
+            pagination: Pagination,
+            #[serde(flatten)]
+            filter: Filter
+        }
+
+        let expected = Query {
+            q: "shoes".to_string(),
+            pagination: Pagination { page: 2, per_page: 50 },
+            filter: Filter { active: true, tags: vec!["red".to_string()], range: Range { min_price: 9.5, max_price: None } }
+        };
+
+        let encoded = "q=shoes&page=2&per_page=50&active=true&tags.0=red&tags.%24length=1&min_price=9.5&max_price=null";
+        assert_eq!(to_string(&expected).unwrap(), encoded);
+        assert_eq!(from_str::<Query>(encoded).unwrap(), expected);
+
+        let got = from_str::<Query>("per_page=50&q=shoes&tags.0=red&tags.%24length=1&page=2&active=true&min_price=9.5&max_price=null").unwrap();
+        assert_eq!(got, expected);
+
+        let config = Config::new().sequence_style(SequenceStyle::Repeated).sequence_length(SequenceLength::Inferred { allow_gaps: false });
+        let got = config.from_str::<Query>("q=shoes&page=2&per_page=50&active=true&tags=red&tags=blue&min_price=9.5&max_price=20").unwrap();
+        assert_eq!(got.filter.tags, vec!["red", "blue"]);
+        assert_eq!(got.filter.range.max_price, Some(20.0));
+
+        let err = from_str::<Query>("q=shoes&page=two&per_page=50&active=true&tags.%24length=0&min_price=1").unwrap_err();
+        assert!(err.to_string().contains("expected u32"), "{err}");
+    }
+
+    #[test]
+    fn test_deserialize_flatten_map() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Scores {
+            player: String,
+            #[serde(flatten)]
+            scores: HashMap<String, i32>
+        }
+
+        let expected = Scores { player: "ann".to_string(), scores: HashMap::from([("round1".to_string(), -3)]) };
+        assert_eq!(to_string(&expected).unwrap(), "player=ann&round1=-3");
+        assert_eq!(from_str::<Scores>("round1=-3&player=ann").unwrap(), expected);
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Tracked {
+            id: u32,
+            #[serde(flatten)]
+            extra: HashMap<String, Value>
+        }
+
+        // Unknown keys of any shape are collected by the catch-all, as written.
+        let got = from_str::<Tracked>("id=4&utm=007&ok=true&ref.id=4&ref.src=mail").unwrap();
+        let expected = Tracked {
+            id: 4,
+            extra: HashMap::from([
+                ("utm".to_string(), Value::from("007")),
+                ("ok".to_string(), Value::from("true")),
+                ("ref".to_string(), Value::Map(Map::from_iter([("id", "4"), ("src", "mail")]))),
+            ])
+        };
+        assert_eq!(got, expected);
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Untyped {
+            source: String,
+            #[serde(flatten)]
+            extra: HashMap<String, String>
+        }
+
+        // A catch-all of strings fails on inferred values, unless inference is disabled.
+        let err = from_str::<Untyped>("source=mail&utm=42").unwrap_err();
+        assert!(err.to_string().contains("expected a string"), "{err}");
+        let got = Config::new().infer_types(false).from_str::<Untyped>("source=mail&utm=42").unwrap();
+        assert_eq!(got, Untyped { source: "mail".to_string(), extra: HashMap::from([("utm".to_string(), "42".to_string())]) });
+    }
+
+    #[test]
+    fn test_deserialize_none() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Form {
+            name: Option<String>,
+            age: Option<u8>,
+            note: Option<String>
+        }
+
+        let form = |name: Option<&str>, age, note: Option<&str>| Form { name: name.map(str::to_string), age, note: note.map(str::to_string) };
+
+        assert_eq!(from_str::<Form>("name=null&age=3&note=").unwrap(), form(None, Some(3), Some("")));
+        assert_eq!(from_str::<Form>("age=3").unwrap(), form(None, Some(3), None));
+
+        let config = Config::new().null_as_none(false).empty_as_none(true);
+        assert_eq!(config.from_str::<Form>("name=null&age=&note=").unwrap(), form(Some("null"), None, None));
+        assert_eq!(config.from_str::<Form>("name=Ann").unwrap(), form(Some("Ann"), None, None));
+        assert!(config.from_str::<Form>("age=null").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_inferred_strings() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(tag = "type")]
+        enum Event {
+            Created { id: u32, name: String }
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Inner {
+            name: String
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Outer {
+            #[serde(flatten)]
+            inner: Inner
+        }
+
+        // Serde cannot read an inferred number or bool back into a string.
+        for input in ["type=Created&id=1&name=42", "type=Created&id=1&name=true", "type=Created&id=1&name=null"] {
+            let err = from_str::<Event>(input).unwrap_err();
+            assert!(err.to_string().contains("expected a string"), "{input}: {err}");
+        }
+        assert!(from_str::<Outer>("name=12").is_err());
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(tag = "type")]
+        enum StringEvent {
+            Created {
+                id: u32,
+                #[serde(with = "crate::string")]
+                name: String
+            }
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct StringInner {
+            #[serde(with = "crate::string")]
+            name: String
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct StringOuter {
+            #[serde(flatten)]
+            inner: StringInner
+        }
+
+        for name in ["42", "-1", "1.5", "true", "null", "Ann", "007"] {
+            let got = from_str::<StringEvent>(&format!("type=Created&id=1&name={name}")).unwrap();
+            assert_eq!(got, StringEvent::Created { id: 1, name: name.to_string() });
+            assert_eq!(from_str::<StringOuter>(&format!("name={name}")).unwrap().inner.name, name);
+            assert_eq!(from_str::<StringInner>(&format!("name={name}")).unwrap().name, name);
+        }
+
+        // Numbers are kept as written unless buffered by serde.
+        assert_eq!(from_str::<StringInner>("name=2.50").unwrap().name, "2.50");
+        assert_eq!(from_str::<StringOuter>("name=2.50").unwrap().inner.name, "2.5");
+
+        // Without inference, strings are read as-is, but not the numbers.
+        let config = Config::new().infer_types(false);
+        assert_eq!(config.from_str::<Outer>("name=12").unwrap().inner.name, "12");
+        assert!(config.from_str::<Event>("type=Created&id=1&name=42").is_err());
+    }
+}
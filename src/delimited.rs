@@ -0,0 +1,75 @@
+//! Per-field delimited sequences, for use with `#[serde(with = "...")]`.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Serialize, Deserialize, PartialEq)]
+//! struct Query {
+//!   #[serde(with = "serde_www_form_urlencoded::delimited::comma")]
+//!   ids: Vec<u32>,
+//!   #[serde(with = "serde_www_form_urlencoded::delimited::space")]
+//!   scope: Vec<String>
+//! }
+//!
+//! let query = Query { ids: vec![1, 2, 3], scope: vec!["read".to_string(), "write".to_string()] };
+//! let encoded = serde_www_form_urlencoded::to_string(&query).unwrap();
+//! assert_eq!(encoded, "ids=1%2C2%2C3&scope=read+write");
+//! assert_eq!(serde_www_form_urlencoded::from_str::<Query>(&encoded).unwrap(), query);
+//! ```
+use serde::{Deserializer, Serialize, Serializer, de::{DeserializeOwned, Error as _}, ser::Error as _};
+
+use crate::{Config, Error, SequenceStyle, Value, to_value};
+
+/// Serialize a sequence of single values joined by the delimiter.
+pub fn serialize<T, S, const DELIMITER: char>(items: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer {
+    let items = match to_value(&items).map_err(S::Error::custom)? {
+        Value::Seq(items) => items,
+        _ => return Err(S::Error::custom("expecting a sequence")),
+    };
+
+    let items = items.iter()
+        .map(|item| item.as_str().ok_or_else(|| S::Error::custom("expecting a sequence of single values")))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if items.iter().any(|item| item.contains(DELIMITER)) {
+        return Err(S::Error::custom(Error::DelimiterInValue { delimiter: DELIMITER }))
+    }
+
+    serializer.serialize_str(&items.join(&DELIMITER.to_string()))
+}
+
+/// Deserialize a sequence from a single value split on the delimiter.
+pub fn deserialize<'de, T, D, const DELIMITER: char>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    T: DeserializeOwned,
+    D: Deserializer<'de> {
+    let value = crate::string::deserialize(deserializer)?;
+    Config::new()
+        .sequence_style(SequenceStyle::Delimited(DELIMITER))
+        .from_value(Value::Single(value))
+        .map_err(D::Error::custom)
+}
+
+macro_rules! delimited {
+    ($name:ident, $delimiter:literal, $doc:literal) => {
+        #[doc = $doc]
+        pub mod $name {
+            use serde::{Deserializer, Serialize, Serializer, de::DeserializeOwned};
+
+            pub fn serialize<T: Serialize, S: Serializer>(items: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+                super::serialize::<T, S, $delimiter>(items, serializer)
+            }
+
+            pub fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<T>, D::Error> {
+                super::deserialize::<T, D, $delimiter>(deserializer)
+            }
+        }
+    };
+}
+
+delimited!(comma, ',', "Sequences joined by commas (`ids=1,2,3`).");
+delimited!(space, ' ', "Sequences joined by spaces (`scope=read+write`).");
+delimited!(pipe, '|', "Sequences joined by pipes (`ids=1|2|3`).");
@@ -0,0 +1,118 @@
+use std::borrow::Cow;
+
+use crate::SpaceEncoding;
+
+const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Returns true if the byte is left as-is by the application/x-www-form-urlencoded percent-encode set.
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'*' | b'-' | b'.' | b'_')
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None
+    }
+}
+
+/// Percent-encode a string with the application/x-www-form-urlencoded percent-encode set.
+pub(crate) fn encode(input: &str, space: SpaceEncoding) -> Cow<'_, str> {
+    if input.bytes().all(is_unreserved) {
+        return Cow::Borrowed(input)
+    }
+
+    let mut output = String::with_capacity(input.len() * 3);
+    for byte in input.bytes() {
+        if is_unreserved(byte) {
+            output.push(byte as char);
+        } else if byte == b' ' && space == SpaceEncoding::Plus {
+            output.push('+');
+        } else {
+            output.push('%');
+            output.push(HEX[(byte >> 4) as usize] as char);
+            output.push(HEX[(byte & 0x0F) as usize] as char);
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Percent-encode the delimiter of a delimited value, leaving the ones which are safe in a value as-is.
+pub(crate) fn encode_delimiter(delimiter: char, space: SpaceEncoding) -> Cow<'static, str> {
+    match delimiter {
+        ',' | '|' | ';' | ':' | '/' => Cow::Owned(delimiter.to_string()),
+        _ => Cow::Owned(encode(&delimiter.to_string(), space).into_owned()),
+    }
+}
+
+/// Percent-decode a string, `+` being decoded as a space.
+///
+/// A `%` which is not followed by two hexadecimal digits is kept as-is, and
+/// decoded byte sequences which are not valid UTF-8 are replaced by U+FFFD,
+/// as specified by the WHATWG URL standard.
+pub(crate) fn decode(input: &str) -> Cow<'_, str> {
+    if !input.contains(['%', '+']) {
+        return Cow::Borrowed(input)
+    }
+
+    let bytes = input.as_bytes();
+    let mut output: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && let (Some(hi), Some(lo)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+            output.push(hi << 4 | lo);
+            i += 3;
+            continue;
+        }
+        output.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+        i += 1;
+    }
+
+    Cow::Owned(String::from_utf8_lossy(&output).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SpaceEncoding;
+    use super::{decode, encode, encode_delimiter};
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("arg0.arg1", SpaceEncoding::Plus), "arg0.arg1");
+        assert_eq!(encode("a&b=c d%", SpaceEncoding::Percent), "a%26b%3Dc%20d%25");
+        assert_eq!(encode("Jérôme", SpaceEncoding::Percent), "J%C3%A9r%C3%B4me");
+    }
+
+    #[test]
+    fn test_encode_space_as_plus() {
+        assert_eq!(encode("a b+c", SpaceEncoding::Plus), "a+b%2Bc");
+    }
+
+    #[test]
+    fn test_encode_delimiter() {
+        assert_eq!(encode_delimiter(',', SpaceEncoding::Plus), ",");
+        assert_eq!(encode_delimiter('|', SpaceEncoding::Plus), "|");
+        assert_eq!(encode_delimiter(' ', SpaceEncoding::Percent), "%20");
+        assert_eq!(encode_delimiter('&', SpaceEncoding::Plus), "%26");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("J%C3%A9r%C3%B4me"), "Jérôme");
+        assert_eq!(decode("a%26b%3dc"), "a&b=c");
+        assert_eq!(decode("a+b%2Bc%20d"), "a b+c d");
+    }
+
+    #[test]
+    fn test_decode_invalid_sequences() {
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%4"), "%4");
+        assert_eq!(decode("%zz%41"), "%zzA");
+        assert_eq!(decode("%FF"), "\u{FFFD}");
+    }
+}
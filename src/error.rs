@@ -1,5 +1,75 @@
 use std::fmt::Display;
 
+use crate::lexer::Span;
+
+/// Number of bytes of context kept on each side of an error in [`Location::fragment`].
+const FRAGMENT_CONTEXT: usize = 40;
+
+/// Location of a decoding error in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    offset: usize,
+    len: usize,
+    fragment: Option<(String, usize)>
+}
+
+impl Location {
+    /// Byte offset of the offending slice in the input.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Byte length of the offending slice in the input.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The `key=value` slice of the input surrounding the error, if known.
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_ref().map(|(fragment, _)| fragment.as_str())
+    }
+
+    fn attach_input(&mut self, input: &str) {
+        if self.fragment.is_some() || self.offset + self.len > input.len() {
+            return
+        }
+
+        let end = self.offset + self.len;
+
+        let mut start = input[..self.offset].rfind('&').map(|i| i + 1).unwrap_or(0);
+        start = start.max(self.offset.saturating_sub(FRAGMENT_CONTEXT));
+        while !input.is_char_boundary(start) { start -= 1; }
+
+        let mut stop = input[end..].find('&').map(|i| end + i).unwrap_or(input.len());
+        stop = stop.min(end + FRAGMENT_CONTEXT);
+        while !input.is_char_boundary(stop) { stop += 1; }
+
+        self.fragment = Some((input[start..stop].to_string(), self.offset - start));
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at offset {}", self.offset)?;
+
+        if let Some((fragment, column)) = &self.fragment {
+            let padding = fragment[..*column].chars().count();
+            let width = fragment[*column..]
+                .char_indices()
+                .take_while(|(i, _)| *i < self.len)
+                .count()
+                .max(1);
+            write!(f, "\n    {fragment}\n    {}{}", " ".repeat(padding), "^".repeat(width))?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Error {
     ExpectingI8,
@@ -16,11 +86,20 @@ pub enum Error {
     ExpectingChar,
     ExpectingBool,
     ExpectingMap,
+    ExpectingEnum,
     ExpectingString,
     ExpectingUtf8String,
     MissingSequenceLength,
     MissingSequenceItem,
     MissingMapValue,
+    /// A joined value contains its delimiter, so that it could not be split back.
+    DelimiterInValue { delimiter: char },
+    UnexpectedToken { expected: &'static str, found: String, offset: usize },
+    UnterminatedString { offset: usize },
+    /// An error raised while decoding a specific slice of the input.
+    Located { error: Box<Error>, location: Location },
+    /// An error raised while decoding the field at the given dotted path.
+    Field { error: Box<Error>, path: String },
     IoError(String),
     Custom(String)
 }
@@ -42,10 +121,16 @@ impl std::fmt::Display for Error {
             Error::ExpectingChar => f.write_str("expecting char"),
             Error::ExpectingBool => f.write_str("expecting bool"),
             Error::ExpectingMap => f.write_str("expecting map"),
+            Error::ExpectingEnum => f.write_str("expecting enum"),
             Error::ExpectingString => f.write_str("expecting string"),
             Error::MissingSequenceLength => f.write_str("expecting $length"),
             Error::MissingSequenceItem => f.write_str("expecting sequence item"),
             Error::MissingMapValue => f.write_str("expecting map value"),
+            Error::DelimiterInValue { delimiter } => write!(f, "value contains the delimiter `{delimiter}`"),
+            Error::UnexpectedToken { expected, found, .. } => write!(f, "expecting {expected}, found {found}"),
+            Error::UnterminatedString { .. } => f.write_str("unterminated string"),
+            Error::Located { error, location } => write!(f, "{error} {location}"),
+            Error::Field { error, path } => write!(f, "{path}: {error}"),
             Error::Custom(custom) => custom.fmt(f),
             Error::ExpectingUtf8String => f.write_str("expecting bytes sequence to be an encoded utf-8 string"),
             Error::IoError(msg) => write!(f, "IO error : {msg}"),
@@ -53,6 +138,79 @@ impl std::fmt::Display for Error {
     }
 }
 
+impl Error {
+    /// Attach the location of the offending slice, unless the error is already located.
+    pub(crate) fn at(self, span: Span) -> Self {
+        if self.location().is_some() {
+            return self
+        }
+
+        Error::Located {
+            error: Box::new(self),
+            location: Location { offset: span.start, len: span.end - span.start, fragment: None }
+        }
+    }
+
+    /// Prefix the path of the field being decoded with the key of its parent.
+    pub(crate) fn in_field(self, key: &str) -> Self {
+        match self {
+            Error::Field { error, path } => Error::Field { error, path: format!("{key}.{path}") },
+            Error::Located { error, location } if error.path().is_some() => Error::Located {
+                error: Box::new(error.in_field(key)),
+                location
+            },
+            error => Error::Field { error: Box::new(error), path: key.to_string() }
+        }
+    }
+
+    /// Attach the surrounding fragment of the input to a located error.
+    pub(crate) fn with_input(mut self, input: &str) -> Self {
+        if let Some(location) = self.location_mut() {
+            location.attach_input(input);
+        }
+        self
+    }
+
+    fn location_mut(&mut self) -> Option<&mut Location> {
+        match self {
+            Error::Located { location, .. } => Some(location),
+            Error::Field { error, .. } => error.location_mut(),
+            _ => None
+        }
+    }
+
+    /// Location of the error in the input, if known.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Error::Located { location, .. } => Some(location),
+            Error::Field { error, .. } => error.location(),
+            _ => None
+        }
+    }
+
+    /// Dotted path of the field which failed to decode, if any.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::Field { path, .. } => Some(path),
+            Error::Located { error, .. } => error.path(),
+            _ => None
+        }
+    }
+
+    /// Byte offset of the error in the input, if known.
+    pub fn offset(&self) -> Option<usize> {
+        self.location().map(Location::offset)
+    }
+
+    /// The error stripped from its location and field path.
+    pub fn kind(&self) -> &Error {
+        match self {
+            Error::Located { error, .. } | Error::Field { error, .. } => error.kind(),
+            error => error
+        }
+    }
+}
+
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         None
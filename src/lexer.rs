@@ -1,16 +1,58 @@
+use std::{borrow::Cow, fmt::Display};
+
+use crate::{Config, Error, Result, StringStyle, encoding};
+
+/// Byte range of a token in the input.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) enum Token {
+pub(crate) enum Token<'a> {
     // =
     Assign,
     // &
     Ampersand,
-    String(String)
+    String(Cow<'a, str>),
+    /// A string written in double quotes.
+    Quoted(Cow<'a, str>)
+}
+
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Assign => f.write_str("`=`"),
+            Token::Ampersand => f.write_str("`&`"),
+            Token::String(value) | Token::Quoted(value) => write!(f, "string `{value}`"),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Token<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::String(Cow::Borrowed(value))
+    }
 }
 
-impl From<&str> for Token {
-    fn from(value: &str) -> Self {
-        Self::String(value.to_string())
+/// Remove the backslashes escaping characters in a quoted string.
+fn unescape(raw: &str) -> String {
+    let mut output = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => output.extend(chars.next()),
+            ch => output.push(ch)
+        }
     }
+    output
 }
 
 #[derive(Debug)]
@@ -19,25 +61,83 @@ enum State {
     AccumulateQuotedString,
     AccumulateUnquotedString,
     EscapingChar,
+    Failed,
 }
 
 pub(crate) struct Lexer<'a> {
-    accumulator: String,
     state: State,
-    input: &'a str
+    string_style: StringStyle,
+    lenient: bool,
+    /// Whether a `=` was already found in the current pair.
+    assigned: bool,
+    /// Whether the quoted string being read contains escaped characters.
+    escaped: bool,
+    source: &'a str,
+    input: &'a str,
+    /// Byte offset of the next character in the original input.
+    offset: usize,
+    /// Byte offset where the token being accumulated starts.
+    start: usize
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &'a str) -> Self {
-        Self {
-            accumulator: Default::default(),
+    pub fn new(input: &'a str, config: Config) -> Self {
+        let mut lexer = Self {
             state: State::Root,
-            input
+            string_style: config.string_style,
+            lenient: config.lenient,
+            assigned: false,
+            escaped: false,
+            source: input,
+            input,
+            offset: 0,
+            start: 0
+        };
+
+        if lexer.lenient && lexer.peek_char() == Some('?') {
+            lexer.next_char();
         }
+
+        lexer
     }
 }
 
-impl Lexer<'_> {
+impl<'a> Lexer<'a> {
+    /// Byte offset of the next token in the input.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Flush the unquoted string read so far into a percent-decoded string token.
+    fn flush(&mut self) -> (Token<'a>, Span) {
+        let raw = &self.source[self.start..self.offset];
+        (Token::String(encoding::decode(raw)), Span::new(self.start, self.offset))
+    }
+
+    /// Flush the quoted string read so far, the closing quote excluded, into a percent-decoded string token.
+    fn flush_quoted(&mut self) -> (Token<'a>, Span) {
+        let raw = &self.source[self.start + 1..self.offset];
+        let value = if std::mem::take(&mut self.escaped) {
+            Cow::Owned(encoding::decode(&unescape(raw)).into_owned())
+        } else {
+            encoding::decode(raw)
+        };
+        self.next_char();
+        (Token::Quoted(value), Span::new(self.start, self.offset))
+    }
+
+    /// Consume a single-character token.
+    fn single(&mut self, token: Token<'a>) -> (Token<'a>, Span) {
+        let start = self.offset;
+        self.next_char();
+        (token, Span::new(start, self.offset))
+    }
+
+    fn unterminated(&mut self) -> Error {
+        self.state = State::Failed;
+        Error::UnterminatedString { offset: self.start }.at(Span::new(self.start, self.offset))
+    }
+
     fn peek_char(&mut self) -> Option<char> {
         self.input.chars().next()
     }
@@ -45,12 +145,13 @@ impl Lexer<'_> {
     fn next_char(&mut self) -> Option<char> {
         let ch = self.peek_char()?;
         self.input = &self.input[ch.len_utf8()..];
+        self.offset += ch.len_utf8();
         Some(ch)
     }
 }
 
 impl<'a> Iterator for Lexer<'a> {
-    type Item = Token;
+    type Item = Result<(Token<'a>, Span)>;
     
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -58,60 +159,67 @@ impl<'a> Iterator for Lexer<'a> {
             match self.state {
                 State::Root => {
                     match ch {
-                        Some('"') => {
+                        Some('"') if self.string_style == StringStyle::Quoted => {
+                            self.start = self.offset;
                             self.next_char();
                             self.state = State::AccumulateQuotedString;
                         },
                         Some('&') => {
-                            self.next_char();
-                            return Some(Token::Ampersand)
+                            self.assigned = false;
+                            return Some(Ok(self.single(Token::Ampersand)))
                         },
-                        Some('=') => {
-                            self.next_char();
-                            return Some(Token::Assign)
+                        Some('=') if !(self.lenient && self.assigned) => {
+                            self.assigned = true;
+                            return Some(Ok(self.single(Token::Assign)))
                         },
                         Some(_) => {
+                            self.start = self.offset;
                             self.state = State::AccumulateUnquotedString;
                         },
-                        None => {
-                            self.next_char();
-                            return None
-                        }
+                        None => return None
                     }
                 },
                 State::AccumulateQuotedString => {
                     match ch {
                         Some('"') => {
-                            self.next_char();
                             self.state = State::Root;
-                            return Some(Token::String(std::mem::take(&mut self.accumulator)));
+                            return Some(Ok(self.flush_quoted()));
                         },
                         Some('\\') => {
                             self.next_char();
+                            self.escaped = true;
                             self.state = State::EscapingChar; 
                         },
-                        _ => {
+                        Some(_) => {
                             self.next_char();
-                            self.accumulator.push(ch.unwrap());
-                        }
+                        },
+                        None => return Some(Err(self.unterminated()))
                     }
                 },
                 State::AccumulateUnquotedString => {
-                    if ch == Some('&') || ch == Some('=') || ch == None {
-                        self.state = State::Root;
-                        return Some(Token::String(std::mem::take(&mut self.accumulator)));
+                    match ch {
+                        Some('=') if self.lenient && self.assigned => {
+                            self.next_char();
+                        },
+                        Some('&') | Some('=') | None => {
+                            self.state = State::Root;
+                            return Some(Ok(self.flush()));
+                        },
+                        Some(_) => {
+                            self.next_char();
+                        }
                     }
-                    
-                    self.next_char();
-                    self.accumulator.push(ch.unwrap());
                 },
                 State::EscapingChar => {
-                    if let Some(c) = ch {
-                        self.next_char();
-                        self.state = State::AccumulateQuotedString;
-                        self.accumulator.push(c);
+                    match ch {
+                        Some(_) => {
+                            self.next_char();
+                            self.state = State::AccumulateQuotedString;
+                        },
+                        None => return Some(Err(self.unterminated()))
                     }
                 },
+                State::Failed => return None,
             }
         }
     }
@@ -119,22 +227,113 @@ impl<'a> Iterator for Lexer<'a> {
 
 #[cfg(test)]
 mod tests {
-    use super::{Lexer, Token};
+    use std::borrow::Cow;
+
+    use crate::{Config, Error, Result, StringStyle};
+    use super::{Lexer, Span, Token};
 
     #[test]
     fn test_lexer() {
-        let lexer = Lexer::new("arg0=\"arg2\\\"\"&arg3=10");
+        let lexer = Lexer::new("arg0=\"arg2\\\"\"&arg3=10", Config::new().string_style(StringStyle::Quoted));
         let expected = vec![
             Token::from("arg0"),
             Token::Assign,
-            Token::from("arg2\""),
+            Token::Quoted(Cow::Borrowed("arg2\"")),
             Token::Ampersand,
             Token::from("arg3"),
             Token::Assign,
             Token::from("10")
         ];
-        let got = lexer.collect::<Vec<_>>();
+        let got = lexer.map(|tok| tok.map(|(tok, _)| tok)).collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn test_lexer_percent_decoding() {
+        let lexer = Lexer::new("na%6De=J%C3%A9r%C3%B4me&q=a%26b%3Dc&p=100%", Config::new());
+        let expected = vec![
+            Token::from("name"),
+            Token::Assign,
+            Token::from("Jérôme"),
+            Token::Ampersand,
+            Token::from("q"),
+            Token::Assign,
+            Token::from("a&b=c"),
+            Token::Ampersand,
+            Token::from("p"),
+            Token::Assign,
+            Token::from("100%")
+        ];
+        let got = lexer.map(|tok| tok.map(|(tok, _)| tok)).collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn test_lexer_plain_quotes() {
+        let lexer = Lexer::new("arg0=\"a\"", Config::new());
+        let expected = vec![
+            Token::from("arg0"),
+            Token::Assign,
+            Token::from("\"a\""),
+        ];
+        let got = lexer.map(|tok| tok.map(|(tok, _)| tok)).collect::<Result<Vec<_>>>().unwrap();
 
         assert_eq!(expected, got);
     }
+
+    #[test]
+    fn test_lexer_unterminated_string() {
+        for input in ["arg0=\"abc", "arg0=\"abc\\"] {
+            let got = Lexer::new(input, Config::new().string_style(StringStyle::Quoted)).collect::<Result<Vec<_>>>();
+            assert!(matches!(got.unwrap_err().kind(), Error::UnterminatedString { offset: 5 }));
+        }
+    }
+
+    #[test]
+    fn test_lexer_spans() {
+        let got = Lexer::new("ab=J%C3%A9&c=\"d\"", Config::new().string_style(StringStyle::Quoted))
+            .map(|tok| tok.map(|(_, span)| span))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let expected = vec![
+            Span::new(0, 2),
+            Span::new(2, 3),
+            Span::new(3, 10),
+            Span::new(10, 11),
+            Span::new(11, 12),
+            Span::new(12, 13),
+            Span::new(13, 16),
+        ];
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn test_lexer_lenient() {
+        let lexer = Lexer::new("?a=b=c&d==", Config::new().lenient(true));
+        let expected = vec![
+            Token::from("a"),
+            Token::Assign,
+            Token::from("b=c"),
+            Token::Ampersand,
+            Token::from("d"),
+            Token::Assign,
+            Token::from("="),
+        ];
+        let got = lexer.map(|tok| tok.map(|(tok, _)| tok)).collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn test_lexer_borrows_input() {
+        let tokens = Lexer::new("a=b&c=d%20e", Config::new())
+            .map(|tok| tok.map(|(tok, _)| tok))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        assert!(matches!(tokens[2], Token::String(Cow::Borrowed("b"))));
+        assert!(matches!(tokens[6], Token::String(Cow::Owned(_))));
+    }
 }
\ No newline at end of file
@@ -31,7 +31,7 @@
 //! 
 //! assert_eq!(
 //!     &serde_www_form_urlencoded::to_string(&ITEM).unwrap(),
-//!     "foo0.bar0=true&foo0.bar1=\"test\"&foo1=2"
+//!     "foo0.bar0=true&foo0.bar1=test&foo1=2"
 //! );
 //! ```
 //! 
@@ -49,21 +49,68 @@
 //! 
 //! assert_eq!(
 //!     &serde_www_form_urlencoded::to_string(&ITEM).unwrap(),
-//!     "foo0.0=0&foo0.1=1&foo0.2=2&foo0.3=3&foo0.4=4&foo0.$length=5"
+//!     "foo0.0=0&foo0.1=1&foo0.2=2&foo0.3=3&foo0.4=4&foo0.%24length=5"
 //! );
 //! ```
+//!
+//! # Enums
+//! Internally tagged and untagged enums are read before their variant is known, the types of their
+//! values being [inferred](Config::infer_types). As serde cannot read an inferred number or bool
+//! back into a `String`, such a field fails on values like `42` unless it uses the [`string`] helper.
+//! The same goes for flattened fields.
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Debug, Deserialize, PartialEq)]
+//! #[serde(untagged)]
+//! enum Target {
+//!   Address { zip: String },
+//!   Code {
+//!     #[serde(with = "serde_www_form_urlencoded::string")]
+//!     code: String
+//!   }
+//! }
+//!
+//! assert!(serde_www_form_urlencoded::from_str::<Target>("zip=75001").is_err());
+//! assert_eq!(
+//!     serde_www_form_urlencoded::from_str::<Target>("code=75001").unwrap(),
+//!     Target::Code { code: "75001".to_string() }
+//! );
+//! ```
+//!
+//! # Dynamic data
+//! Any form can be read into a [`Value`] when its shape is not known in advance, and written back.
+//!
+//! ```
+//! use serde_www_form_urlencoded::Value;
+//!
+//! let form: Value = serde_www_form_urlencoded::from_str("foo0.bar0=true&foo1=2").unwrap();
+//! assert_eq!(form["foo0.bar0"].as_str(), Some("true"));
+//! assert_eq!(serde_www_form_urlencoded::to_string(&form).unwrap(), "foo0.bar0=true&foo1=2");
+//! ```
 
+mod config;
 mod error;
+mod encoding;
+mod path;
 mod parser;
 mod lexer;
 mod de;
 mod ser;
+mod value;
+pub mod delimited;
+pub mod repeated;
+pub mod string;
 
-pub use error::Error;
+pub use config::{Config, KeySyntax, ObjectStyle, ParameterStyle, SequenceLength, SequenceStyle, SpaceEncoding, StringStyle};
+pub use error::{Error, Location};
+pub use value::{Leaves, Map, Value};
+pub use parser::{Pairs, pairs};
 pub type Result<T> = std::result::Result<T, Error>;
 
-pub use ser::{FormEncoder as Serializer, to_string, to_writer};
-pub use de::{from_str, from_bytes, from_reader, FormDecoder as Deserializer};
+pub use ser::{FormEncoder as Serializer, to_string, to_value, to_writer};
+pub use de::{from_str, from_bytes, from_reader, from_value, FormDecoder as Deserializer};
 
 #[cfg(test)]
 mod tests {
@@ -71,22 +118,22 @@ mod tests {
 
     #[derive(Debug, Serialize, Deserialize, PartialEq)]
     pub struct Foo {
-        arg0: bool,
-        arg1: u8,
-        arg2: u16,
-        arg3: u32,
-        arg4: u64,
-        arg5: f32,
-        arg6: f64,
-        arg7: String,
-        arg8: Nested,
-        arg9: Vec<Nested>
+        pub arg0: bool,
+        pub arg1: u8,
+        pub arg2: u16,
+        pub arg3: u32,
+        pub arg4: u64,
+        pub arg5: f32,
+        pub arg6: f64,
+        pub arg7: String,
+        pub arg8: Nested,
+        pub arg9: Vec<Nested>
     }
 
     #[derive(Debug, Serialize, Deserialize, PartialEq)]
     pub struct Nested {
-        arg0: String,
-        arg1: f32
+        pub arg0: String,
+        pub arg1: f32
     }
     
     pub fn fixture() -> Foo {
@@ -107,6 +154,8 @@ mod tests {
         }
     }
 
-    pub const ENCODED: &'static str = "arg0=false&arg1=8&arg2=9&arg3=10&arg4=11&arg5=1.01&arg6=1.02&arg7=\"test\"&arg8.arg0=\"nested_test\"&arg8.arg1=18.01&arg9.0.arg0=\"item0\"&arg9.0.arg1=20.5&arg9.1.arg0=\"item1\"&arg9.1.arg1=10.5&arg9.$length=2";
+    pub const ENCODED: &str = "arg0=false&arg1=8&arg2=9&arg3=10&arg4=11&arg5=1.01&arg6=1.02&arg7=test&arg8.arg0=nested_test&arg8.arg1=18.01&arg9.0.arg0=item0&arg9.0.arg1=20.5&arg9.1.arg0=item1&arg9.1.arg1=10.5&arg9.%24length=2";
+
+    pub const ENCODED_QUOTED: &str = "arg0=false&arg1=8&arg2=9&arg3=10&arg4=11&arg5=1.01&arg6=1.02&arg7=\"test\"&arg8.arg0=\"nested_test\"&arg8.arg1=18.01&arg9.0.arg0=\"item0\"&arg9.0.arg1=20.5&arg9.1.arg0=\"item1\"&arg9.1.arg1=10.5&arg9.%24length=2";
     
 }
\ No newline at end of file
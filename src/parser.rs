@@ -1,18 +1,43 @@
-use std::collections::HashMap;
-
-use crate::lexer::{Lexer, Token};
-
-use super::Result;
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{Config, SequenceStyle, lexer::{Lexer, Span, Token}, path::{self, Leaf}};
+
+use super::{Error, Result};
+
+/// How a value is written in the input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Notation {
+    /// `key=value`, or `key=` for an empty value.
+    #[default]
+    Assigned,
+    /// A key without `=`, only accepted when lenient.
+    Valueless,
+    /// `key="value"`, when strings are quoted.
+    Quoted,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct KeyValue {
-    pub key: String,
-    pub value: String
+pub(crate) struct KeyValue<'a> {
+    pub key: Cow<'a, str>,
+    pub value: Cow<'a, str>,
+    /// Location of the value in the input.
+    pub span: Span,
+    pub notation: Notation
 }
 
-impl KeyValue {
-    pub fn new<Key: ToString, Value: ToString>(key: Key, value: Value) -> Self {
-        Self {key: key.to_string(), value: value.to_string()}
+impl<'a> KeyValue<'a> {
+    pub fn new<Key: Into<Cow<'a, str>>, Value: Into<Cow<'a, str>>>(key: Key, value: Value) -> Self {
+        Self {key: key.into(), value: value.into(), span: Span::default(), notation: Notation::default()}
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn with_notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+        self
     }
 }
 
@@ -21,62 +46,97 @@ enum State {
     KeyFound,
     AssignFound,
     ExpectingAmpersandOrEos,
+    Done,
 }
 
 pub(crate) struct Parser<'a>{
-    stack: Vec<String>,
+    stack: Vec<Cow<'a, str>>,
     state: State,
+    lenient: bool,
     lexer: Lexer<'a>
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(input: &'a str) -> Self {
+    pub fn new(input: &'a str, config: Config) -> Self {
         Self {
             stack: vec![],
             state: State::Root,
-            lexer: Lexer::new(input)
+            lenient: config.lenient,
+            lexer: Lexer::new(input, config)
         }
     }
 }
 
+impl<'a> Parser<'a> {
+    /// Emit the pending key with its value.
+    fn value(&mut self, value: Cow<'a, str>, span: Span, notation: Notation) -> Option<Result<KeyValue<'a>>> {
+        let key = self.stack.pop().unwrap();
+        self.state = State::ExpectingAmpersandOrEos;
+        Some(Ok(KeyValue::new(key, value).with_span(span).with_notation(notation)))
+    }
+
+    /// Emit the pending key with an empty value, for `key=` or, when lenient, `key`.
+    fn empty_value(&mut self, next_state: State, span: Span, notation: Notation) -> Option<Result<KeyValue<'a>>> {
+        let key = self.stack.pop().unwrap();
+        self.state = next_state;
+        Some(Ok(KeyValue::new(key, "").with_span(Span::new(span.start, span.start)).with_notation(notation)))
+    }
+
+    fn unexpected(&mut self, expected: &'static str, found: Option<Token>, span: Span) -> Option<Result<KeyValue<'a>>> {
+        self.state = State::Done;
+        let found = found.map(|tok| tok.to_string()).unwrap_or_else(|| "end of input".to_string());
+        Some(Err(Error::UnexpectedToken { expected, found, offset: span.start }.at(span)))
+    }
+}
+
 impl<'a> Iterator for Parser<'a> {
-    type Item = Result<KeyValue>;
+    type Item = Result<KeyValue<'a>>;
     
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let maybe_tok = self.lexer.next();
+            let offset = self.lexer.offset();
+            let (maybe_tok, span) = match self.lexer.next() {
+                Some(Ok((tok, span))) => (Some(tok), span),
+                Some(Err(err)) => {
+                    self.state = State::Done;
+                    return Some(Err(err))
+                },
+                None => (None, Span::new(offset, offset))
+            };
 
             match self.state {
                 State::Root => {
                     match maybe_tok {
-                        Some(Token::String(key)) => {
+                        Some(Token::String(key) | Token::Quoted(key)) => {
                             self.stack.push(key);
                             self.state = State::KeyFound;
                         },
+                        Some(Token::Ampersand) if self.lenient => {},
+                        Some(Token::Assign) if self.lenient => {
+                            self.stack.push(Cow::Borrowed(""));
+                            self.state = State::AssignFound;
+                        },
                         None => return None,
-                        _ => {
-                            todo!("Expecting string token")
-                        }
+                        tok => return self.unexpected("a key", tok, span)
                     }
                 },
                 State::KeyFound => {
                     match maybe_tok {
                         Some(Token::Assign) => {
                             self.state = State::AssignFound;
-                        }
-                        _ => {
-                            todo!("Expecting assign token")
-                        }
+                        },
+                        Some(Token::Ampersand) if self.lenient => return self.empty_value(State::Root, span, Notation::Valueless),
+                        None if self.lenient => return self.empty_value(State::Done, span, Notation::Valueless),
+                        tok => return self.unexpected("`=`", tok, span)
                     }
                 },
                 State::AssignFound => {
                     match maybe_tok {
-                        Some(Token::String(value)) => {
-                            let key = self.stack.pop().unwrap();
-                            self.state = State::ExpectingAmpersandOrEos;
-                            return Some(Ok(KeyValue::new(key, value)))
-                        },
-                        _ => todo!("Expecting a string token")
+                        Some(Token::String(value)) => return self.value(value, span, Notation::Assigned),
+                        Some(Token::Quoted(value)) => return self.value(value, span, Notation::Quoted),
+                        Some(Token::Ampersand) => return self.empty_value(State::Root, span, Notation::Assigned),
+                        None => return self.empty_value(State::Done, span, Notation::Assigned),
+                        tok => return self.unexpected("a value", tok, span)
                     }
                 },
                 State::ExpectingAmpersandOrEos => {
@@ -85,45 +145,137 @@ impl<'a> Iterator for Parser<'a> {
                             self.state = State::Root;
                         },
                         None => return None,
-                        _ => todo!("Expecting either & or eos")
+                        tok => return self.unexpected("`&` or end of input", tok, span)
                     }
-                }
+                },
+                State::Done => return None
             }
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub(crate) enum Value {
-    Single(String),
-    Map(Map),
+/// Iterator over the percent-decoded key/value pairs of an input, see [`pairs`](crate::pairs).
+pub struct Pairs<'a> {
+    input: &'a str,
+    parser: Parser<'a>
 }
 
-impl From<String> for Value {
-    fn from(value: String) -> Self {
-        Self::Single(value)
+impl<'a> Iterator for Pairs<'a> {
+    type Item = Result<(Cow<'a, str>, Cow<'a, str>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let kv = self.parser.next()?;
+        Some(kv.map(|kv| (kv.key, kv.value)).map_err(|err| err.with_input(self.input)))
     }
 }
 
-impl FromIterator<KeyValue> for Value {
-    fn from_iter<T: IntoIterator<Item = KeyValue>>(iter: T) -> Self {
-        let mut v = Value::map();
-        for kv in iter.into_iter() {
-            let path = kv.key.split(".").collect::<Vec<_>>();
-            v.set(path.as_slice(), kv.value);
+impl Config {
+    /// Iterate over the key/value pairs of the input with this configuration.
+    pub fn pairs<'a>(&self, input: &'a str) -> Pairs<'a> {
+        Pairs { input, parser: Parser::new(input, *self) }
+    }
+}
+
+/// Iterate over the percent-decoded key/value pairs of the input, without going through serde.
+///
+/// ```
+/// let pairs = serde_www_form_urlencoded::pairs("name=Jane+Doe&tags.0=a%26b")
+///     .collect::<serde_www_form_urlencoded::Result<Vec<_>>>()
+///     .unwrap();
+///
+/// assert_eq!(pairs, vec![("name".into(), "Jane Doe".into()), ("tags.0".into(), "a&b".into())]);
+/// ```
+pub fn pairs(input: &str) -> Pairs<'_> {
+    Config::default().pairs(input)
+}
+
+#[derive(Debug)]
+pub(crate) enum Value<'a> {
+    /// A single value, its location in the input and how it is written.
+    Single(Cow<'a, str>, Span, Notation),
+    Map(Map<'a>),
+    /// The values of a repeated key.
+    Seq(Vec<Value<'a>>),
+}
+
+/// Values are compared regardless of their location in the input.
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Single(lhs, ..), Value::Single(rhs, ..)) => lhs == rhs,
+            (Value::Map(lhs), Value::Map(rhs)) => lhs == rhs,
+            (Value::Seq(lhs), Value::Seq(rhs)) => lhs == rhs,
+            _ => false
+        }
+    }
+}
+
+impl Eq for Value<'_> {}
+
+impl<'a> From<Cow<'a, str>> for Value<'a> {
+    fn from(value: Cow<'a, str>) -> Self {
+        Self::Single(value, Span::default(), Notation::default())
+    }
+}
+
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::Single(Cow::Borrowed(value), Span::default(), Notation::default())
+    }
+}
+
+impl From<crate::Value> for Value<'static> {
+    fn from(value: crate::Value) -> Self {
+        match value {
+            crate::Value::Single(value) => Cow::<str>::Owned(value).into(),
+            crate::Value::Quoted(value) => Value::Single(Cow::Owned(value), Span::default(), Notation::Quoted),
+            crate::Value::Map(map) => {
+                let mut v = Map::default();
+                for (key, value) in map {
+                    v.insert(Cow::Owned(key), value.into());
+                }
+                Value::Map(v)
+            },
+            crate::Value::Seq(values) => Value::Seq(values.into_iter().map(Value::from).collect()),
         }
-        v
     }
 }
 
-impl Value {
+impl<'a> FromIterator<KeyValue<'a>> for Value<'a> {
+    fn from_iter<T: IntoIterator<Item = KeyValue<'a>>>(iter: T) -> Self {
+        Value::from_pairs(iter.into_iter().map(Ok), Config::default()).unwrap()
+    }
+}
+
+impl<'a> Value<'a> {
     pub fn map() -> Self {
         Self::Map(Map::default())
     }
 
-    pub fn set<SingleValue: ToString>(&mut self, path: &[&str], value: SingleValue) {
+    /// Collect the pairs into a tree, repeated keys being handled according to the sequence style.
+    pub fn from_pairs<I>(pairs: I, config: Config) -> Result<Self> 
+    where I: IntoIterator<Item = Result<KeyValue<'a>>>
+    {
+        let mut v = Value::map();
+        for kv in pairs {
+            let kv = kv?;
+            let (path, leaf) = path::split(&kv.key, config);
+            let value = Value::Single(kv.value, kv.span, kv.notation);
+            match (leaf, config.sequence_style) {
+                (Leaf::Length, _) => v.set_length(path.as_slice(), value),
+                (Leaf::Push, _) => v.push(path.as_slice(), value),
+                (Leaf::Set, SequenceStyle::Indexed | SequenceStyle::Delimited(_)) => v.set(path.as_slice(), value),
+                (Leaf::Set, SequenceStyle::Repeated) => v.append(path.as_slice(), value),
+            }
+        }
+        Ok(v)
+    }
+
+    pub fn set<K, V>(&mut self, path: &[K], value: V) 
+    where K: AsRef<str> + Clone + Into<Cow<'a, str>>, V: Into<Value<'a>> 
+    {
         if path.is_empty() {
-            *self = Value::Single(value.to_string());
+            *self = value.into();
             return;
         }
 
@@ -131,38 +283,95 @@ impl Value {
             *self = Self::Map(Map::default());
         }
 
-        self.try_as_mut_map().unwrap().set(&path, value.to_string());
+        self.try_as_mut_map().unwrap().set(path, value.into());
+
+    }
+
+    /// Add a value at the given path, the values of a repeated key being collected into a sequence.
+    pub fn append<K>(&mut self, path: &[K], value: Value<'a>) 
+    where K: AsRef<str> + Clone + Into<Cow<'a, str>>
+    {
+        if self.try_as_mut_map().is_none() {
+            *self = Self::Map(Map::default());
+        }
+
+        self.try_as_mut_map().unwrap().add(path, value, false);
+    }
+
+    /// Set the length of the sequence at the given path.
+    pub fn set_length<K, V>(&mut self, path: &[K], value: V) 
+    where K: AsRef<str> + Clone + Into<Cow<'a, str>>, V: Into<Value<'a>>
+    {
+        if self.try_as_mut_map().is_none() {
+            *self = Self::Map(Map::default());
+        }
 
+        let map = self.try_as_mut_map().unwrap();
+        match path.split_first() {
+            Some((key, rest)) => {
+                if map.get(key.as_ref()).is_none() {
+                    map.insert(key.clone().into(), Value::map());
+                }
+                map.get_mut(key.as_ref()).unwrap().set_length(rest, value);
+            },
+            None => map.length = Some(Box::new(value.into())),
+        }
     }
+
+    /// Push a value into the sequence at the given path, creating it if it is missing.
+    pub fn push<K>(&mut self, path: &[K], value: Value<'a>) 
+    where K: AsRef<str> + Clone + Into<Cow<'a, str>>
+    {
+        if self.try_as_mut_map().is_none() {
+            *self = Self::Map(Map::default());
+        }
+
+        self.try_as_mut_map().unwrap().add(path, value, true);
+    }
+
+    /// Location of the value in the input, that is the leftmost location of its single values.
+    ///
+    /// Values which were not parsed from an input have no location.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Value::Single(_, span, _) => Some(*span).filter(|span| *span != Span::default()),
+            Value::Map(map) => map.values().filter_map(Value::span).min_by_key(|span| span.start),
+            Value::Seq(values) => values.iter().filter_map(Value::span).min_by_key(|span| span.start),
+        }
+    }
+    #[cfg(test)]
     pub fn borrow(&self, path: &[&str]) -> Option<&Self> {
         if path.is_empty() {
             return Some(self)
         }
 
         match self {
-            Value::Single(_) => None,
+            Value::Single(..) => None,
             Value::Map(map) => map.borrow(path),
+            Value::Seq(values) => path[0].parse::<usize>().ok()
+                .and_then(|i| values.get(i))
+                .and_then(|v| v.borrow(&path[1..])),
         }
     }
 
 
-    pub fn try_as_single(self) -> Option<String> {
-        if let Self::Single(val) = self {
+    pub fn try_as_single(self) -> Option<Cow<'a, str>> {
+        if let Self::Single(val, ..) = self {
             return Some(val)
         }
 
         None
     }
 
-    pub fn try_as_ref_single(&self) -> Option<&String> {
-        if let Self::Single(val) = self {
+    pub fn try_as_ref_single(&self) -> Option<&str> {
+        if let Self::Single(val, ..) = self {
             return Some(val)
         }
 
         None
     }
 
-    pub fn try_as_map(self) -> Option<Map> {
+    pub fn try_as_map(self) -> Option<Map<'a>> {
          if let Self::Map(val) = self {
             return Some(val)
         }
@@ -170,7 +379,7 @@ impl Value {
         None       
     }
 
-    pub fn try_as_mut_map(&mut self) -> Option<&mut Map> {
+    pub fn try_as_mut_map(&mut self) -> Option<&mut Map<'a>> {
         if let Self::Map(val) = self {
             return Some(val)
         }
@@ -179,54 +388,136 @@ impl Value {
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
-pub(crate) struct Map(HashMap<String, Value>);
+/// A map keeping its entries in insertion order.
+#[derive(Debug, Default)]
+pub(crate) struct Map<'a> {
+    entries: Vec<Option<(Cow<'a, str>, Value<'a>)>>,
+    /// Position of each key in `entries`.
+    index: HashMap<Cow<'a, str>, usize>,
+    /// The length of the sequence this map holds the items of, if any.
+    length: Option<Box<Value<'a>>>
+}
+
+pub(crate) type IntoIter<'a> = std::iter::Flatten<std::vec::IntoIter<Option<(Cow<'a, str>, Value<'a>)>>>;
 
-impl IntoIterator for Map {
-    type Item = (String, Value);
-    type IntoIter = std::collections::hash_map::IntoIter<String, Value>;
+impl<'a> IntoIterator for Map<'a> {
+    type Item = (Cow<'a, str>, Value<'a>);
+    type IntoIter = IntoIter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.entries.into_iter().flatten()
+    }
+}
+
+/// Maps are equal if they hold the same entries in the same order.
+impl PartialEq for Map<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter()) && self.length == other.length
     }
 }
 
-impl Map {
-    pub fn take(&mut self, key: &str) -> Option<(String, Value)> {
-        self.0.remove_entry(key)
+impl Eq for Map<'_> {}
+
+impl<'a> Map<'a> {
+    pub fn iter(&self) -> impl Iterator<Item = (&Cow<'a, str>, &Value<'a>)> {
+        self.entries.iter().flatten().map(|(k, v)| (k, v))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value<'a>> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> {
+        let i = *self.index.get(key)?;
+        self.entries[i].as_ref().map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value<'a>> {
+        let i = *self.index.get(key)?;
+        self.entries[i].as_mut().map(|(_, v)| v)
+    }
+
+    /// Insert a value, keeping the position of the key if it is already present.
+    pub fn insert(&mut self, key: Cow<'a, str>, value: Value<'a>) {
+        match self.get_mut(&key) {
+            Some(current) => *current = value,
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push(Some((key, value)));
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn length(&self) -> Option<&Value<'a>> {
+        self.length.as_deref()
+    }
+
+    pub fn take_length(&mut self) -> Option<Value<'a>> {
+        self.length.take().map(|length| *length)
+    }
+
+    pub fn take(&mut self, key: &str) -> Option<(Cow<'a, str>, Value<'a>)> {
+        let i = self.index.remove(key)?;
+        self.entries[i].take()
     }
     
-    pub fn set(&mut self, path: &[&str], value: String) {
+    pub fn set<K>(&mut self, path: &[K], value: Value<'a>) where K: AsRef<str> + Clone + Into<Cow<'a, str>> {
         if path.len() == 1 {
-            self.0.insert(path[0].to_string(), Value::Single(value));
+            self.insert(path[0].clone().into(), value);
             return;
         }
 
-        let part = path[0].to_string();
-        
-        if self.0.contains_key(&part) {
-            self.0.get_mut(&part).unwrap().set(&path[1..], value);
+        if let Some(current) = self.get_mut(path[0].as_ref()) {
+            current.set(&path[1..], value);
             return;
         }
 
         let mut v = Value::Map(Map::default());
         v.set(&path[1..], value);
-        self.0.insert(part, v);
+        self.insert(path[0].clone().into(), v);
+    }
+
+    /// Add a value, collecting it with the single value or sequence already at the given path.
+    ///
+    /// When nothing is found at the path, the value is set as-is, or in a new sequence if `push` is set.
+    fn add<K>(&mut self, path: &[K], value: Value<'a>, push: bool) where K: AsRef<str> + Clone + Into<Cow<'a, str>> {
+        match self.get_mut(path[0].as_ref()) {
+            Some(current) if path.len() > 1 => {
+                if current.try_as_mut_map().is_none() {
+                    *current = Value::map();
+                }
+                current.try_as_mut_map().unwrap().add(&path[1..], value, push)
+            },
+            Some(Value::Seq(values)) => values.push(value),
+            Some(current @ Value::Single(..)) => {
+                let first = std::mem::replace(current, Value::Seq(Vec::default()));
+                *current = Value::Seq(vec![first, value]);
+            },
+            _ if push => self.set(path, Value::Seq(vec![value])),
+            _ => self.set(path, value)
+        }
     }
-    pub fn borrow(&self, path: &[&str]) -> Option<&Value> {
+
+    #[cfg(test)]
+    pub fn borrow(&self, path: &[&str]) -> Option<&Value<'a>> {
         if path.is_empty() {
             return None
         }
 
         let part = path[0];
-        self.0.get(part).map(|v| v.borrow(&path[1..])).flatten()
+        self.get(part).and_then(|v| v.borrow(&path[1..]))
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{Result, parser::{KeyValue, Value}};
-    use super::Parser;
+    use crate::{Error, Result, parser::{KeyValue, Notation, Value}};
+    use crate::Config;
+    use super::{Parser, pairs};
 
     #[test]
     fn test_parser() {
@@ -248,14 +539,17 @@ mod test {
             KeyValue::new("arg9.$length", "2")       
         ];
 
-        let parser = Parser::new(crate::tests::ENCODED);
-        let got = parser.collect::<Result<Vec<_>>>().unwrap();
+        let parser = Parser::new(crate::tests::ENCODED, Default::default());
+        let got = parser
+            .map(|kv| kv.map(|kv| KeyValue::new(kv.key, kv.value)))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
         assert_eq!(got, expected);        
     }
 
     #[test]
     fn test_collect_nested_value() {
-        let parser = Parser::new(crate::tests::ENCODED);
+        let parser = Parser::new(crate::tests::ENCODED, Default::default());
         
         let mut expected = Value::map();
         expected.set(&["arg0"], "false");
@@ -272,11 +566,82 @@ mod test {
         expected.set(&["arg9", "0", "arg1"], "20.5");
         expected.set(&["arg9", "1", "arg0"], "item1");
         expected.set(&["arg9", "1", "arg1"], "10.5");
-        expected.set(&["arg9", "$length"], "2");
+        expected.set_length(&["arg9"], "2");
 
 
         let got = parser.collect::<Result<Value>>().unwrap();
 
         assert_eq!(expected, got);
     }
+
+    #[test]
+    fn test_parser_malformed_input() {
+        let cases = [
+            ("=x", "a key", "`=`", 0),
+            ("a&b=1", "`=`", "`&`", 1),
+            ("a==b", "a value", "`=`", 2),
+            ("a=1&&b=2", "a key", "`&`", 4),
+            ("a=b=c", "`&` or end of input", "`=`", 3),
+            ("a", "`=`", "end of input", 1),
+        ];
+
+        for (input, exp_expected, exp_found, exp_offset) in cases {
+            match Parser::new(input, Default::default()).collect::<Result<Vec<_>>>().map_err(|err| err.kind().clone()) {
+                Err(Error::UnexpectedToken { expected, found, offset }) => {
+                    assert_eq!((expected, found.as_str(), offset), (exp_expected, exp_found, exp_offset), "{input}");
+                },
+                got => panic!("{input}: unexpected result {got:?}")
+            }
+        }
+    }
+
+    #[test]
+    fn test_parser_lenient() {
+        let expected = vec![
+            KeyValue::new("a", "1"),
+            KeyValue::new("b", "2"),
+            KeyValue::new("debug", "").with_notation(Notation::Valueless),
+            KeyValue::new("x", ""),
+            KeyValue::new("", "y"),
+            KeyValue::new("c", "d=e"),
+            KeyValue::new("f", "").with_notation(Notation::Valueless),
+        ];
+
+        let parser = Parser::new("?a=1&&b=2&debug&x=&=y&c=d=e&&f", Config::new().lenient(true));
+        let got = parser
+            .map(|kv| kv.map(|kv| KeyValue::new(kv.key, kv.value).with_notation(kv.notation)))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(got, expected);
+
+        let parser = Parser::new("a=1&", Config::new().lenient(true));
+        assert_eq!(parser.count(), 1);
+    }
+
+    #[test]
+    fn test_pairs() {
+        let got = pairs("a%2Eb=c+d&e=%C3%A9").collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(got, vec![("a.b".into(), "c d".into()), ("e".into(), "é".into())]);
+
+        let got = Config::new().lenient(true).pairs("?debug&x=1").collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(got, vec![("debug".into(), "".into()), ("x".into(), "1".into())]);
+
+        let err = pairs("a=1&b").collect::<Result<Vec<_>>>().unwrap_err();
+        assert!(matches!(err.kind(), Error::UnexpectedToken { offset: 5, .. }));
+        assert_eq!(err.location().and_then(|loc| loc.fragment()), Some("b"));
+    }
+
+    #[test]
+    fn test_collect_preserves_order() {
+        let parser = Parser::new("z=1&a.y=2&m=3&a.b=4&z=5", Default::default());
+        let got = parser.collect::<Result<Value>>().unwrap().try_as_map().unwrap();
+
+        let keys = got.iter().map(|(k, _)| k.as_ref()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["z", "a", "m"]);
+        assert_eq!(got.borrow(&["z"]).and_then(Value::try_as_ref_single), Some("5"));
+
+        let Some(Value::Map(nested)) = got.borrow(&["a"]) else { panic!("expecting a map") };
+        let keys = nested.iter().map(|(k, _)| k.as_ref()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["y", "b"]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,259 @@
+use std::borrow::Cow;
+
+use crate::{Config, KeySyntax};
+
+/// How the value of a key is stored at the end of its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Leaf {
+    /// The value is set at the path.
+    Set,
+    /// The value is pushed into the sequence at the path (`a[]`).
+    Push,
+    /// The value is the length of the sequence at the path.
+    Length,
+}
+
+/// How an empty segment is escaped, as it would otherwise be read as the parent key (`.x`) or a push (`a[]`).
+const EMPTY_SEGMENT: &str = "\\~";
+
+/// Byte offset of the first occurrence of the pattern which is not escaped by a backslash.
+fn find_unescaped(key: &str, pattern: &str) -> Option<usize> {
+    let mut chars = key.char_indices();
+    while let Some((i, ch)) = chars.next() {
+        if ch == '\\' {
+            chars.next();
+        } else if key[i..].starts_with(pattern) {
+            return Some(i)
+        }
+    }
+    None
+}
+
+/// Split a dotted key on its unescaped separators.
+fn dotted_segments<'k>(mut key: &'k str, separator: &str) -> Vec<&'k str> {
+    let mut segments = Vec::default();
+    while let Some(i) = find_unescaped(key, separator) {
+        segments.push(&key[..i]);
+        key = &key[i + separator.len()..];
+    }
+    segments.push(key);
+    segments
+}
+
+/// Split a `a[b][c]` key into its segments, or none if the brackets are malformed.
+fn bracket_segments(key: &str) -> Option<Vec<&str>> {
+    let Some(start) = find_unescaped(key, "[") else { return Some(vec![key]) };
+    let mut segments = vec![&key[..start]];
+    let mut rest = &key[start..];
+
+    while !rest.is_empty() {
+        let inner = rest.strip_prefix('[')?;
+        let end = find_unescaped(inner, "]")?;
+        segments.push(&inner[..end]);
+        rest = &inner[end + 1..];
+    }
+
+    Some(segments)
+}
+
+/// Remove the backslashes escaping characters in a segment.
+fn unescape(raw: &str) -> Cow<'_, str> {
+    if raw == EMPTY_SEGMENT {
+        return Cow::Borrowed("")
+    }
+
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw)
+    }
+
+    let mut output = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => output.extend(chars.next()),
+            ch => output.push(ch)
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Split a key into its raw segments, and how its value is stored.
+fn segments(key: &str, config: Config) -> (Vec<&str>, Leaf) {
+    let mut segments = match config.key_syntax {
+        KeySyntax::Dotted => dotted_segments(key, config.separator),
+        // A malformed key is kept as-is.
+        KeySyntax::Bracket => bracket_segments(key).unwrap_or_else(|| vec![key]),
+    };
+
+    let leaf = match segments.last() {
+        _ if segments.len() < 2 => Leaf::Set,
+        Some(&"") if config.key_syntax == KeySyntax::Bracket => Leaf::Push,
+        Some(last) if *last == config.length_key => Leaf::Length,
+        _ => Leaf::Set,
+    };
+
+    if leaf != Leaf::Set {
+        segments.pop();
+    }
+
+    (segments, leaf)
+}
+
+/// Split a key into its unescaped path segments, borrowing from the input when possible.
+///
+/// Returns how the value of the key is stored at the end of the path.
+pub(crate) fn split<'a>(key: &Cow<'a, str>, config: Config) -> (Vec<Cow<'a, str>>, Leaf) {
+    match key {
+        Cow::Borrowed(key) => {
+            let (segments, leaf) = segments(key, config);
+            (segments.into_iter().map(unescape).collect(), leaf)
+        },
+        Cow::Owned(key) => {
+            let (segments, leaf) = segments(key, config);
+            (segments.into_iter().map(|part| Cow::Owned(unescape(part).into_owned())).collect(), leaf)
+        },
+    }
+}
+
+/// Split a dotted path into its unescaped segments, the length key having no special meaning.
+pub(crate) fn split_dotted(path: &str, config: Config) -> Vec<Cow<'_, str>> {
+    dotted_segments(path, config.separator).into_iter().map(unescape).collect()
+}
+
+/// Escape a segment with backslashes, so that it is read back as a single segment.
+///
+/// Backslashes, the separator (or brackets) and the length key are escaped, `=` and `&` being
+/// taken care of by percent-encoding. An empty segment is written as `\~`.
+pub(crate) fn escape(segment: &str, config: Config) -> Cow<'_, str> {
+    if segment.is_empty() {
+        return Cow::Borrowed(EMPTY_SEGMENT)
+    }
+
+    let separator = config.separator;
+    let needs_escape = |i: usize| {
+        let rest = &segment[i..];
+        rest.starts_with('\\') || match config.key_syntax {
+            // A separator could also start at the end of the segment and overlap the following one.
+            KeySyntax::Dotted => rest.starts_with(separator) || (rest.len() < separator.len() && separator.starts_with(rest)),
+            KeySyntax::Bracket => rest.starts_with(['[', ']']),
+        }
+    };
+    let reserved = segment == config.length_key;
+
+    if !reserved && !segment.char_indices().any(|(i, _)| needs_escape(i)) {
+        return Cow::Borrowed(segment)
+    }
+
+    let mut output = String::with_capacity(segment.len() + 2);
+    for (i, ch) in segment.char_indices() {
+        if needs_escape(i) || (reserved && i == 0) {
+            output.push('\\');
+        }
+        output.push(ch);
+    }
+    Cow::Owned(output)
+}
+
+/// Append a segment to a key.
+pub(crate) fn join(parent: &str, key: &str, config: Config) -> String {
+    if parent.is_empty() {
+        return key.to_string()
+    }
+
+    match config.key_syntax {
+        KeySyntax::Dotted => format!("{parent}{}{key}", config.separator),
+        KeySyntax::Bracket => format!("{parent}[{key}]"),
+    }
+}
+
+/// Key under which the items of a sequence are written as repeated keys.
+pub(crate) fn repeated(parent: &str, config: Config) -> String {
+    match config.key_syntax {
+        KeySyntax::Dotted => parent.to_string(),
+        KeySyntax::Bracket => format!("{parent}[]"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::{Config, KeySyntax};
+    use super::{Leaf, escape, join, split};
+
+    #[test]
+    fn test_split_bracket() {
+        let config = Config::new().key_syntax(KeySyntax::Bracket);
+        let cases = [
+            ("a", vec!["a"], Leaf::Set),
+            ("a[b][c]", vec!["a", "b", "c"], Leaf::Set),
+            ("a[0]", vec!["a", "0"], Leaf::Set),
+            ("a[]", vec!["a"], Leaf::Push),
+            ("a[$length]", vec!["a"], Leaf::Length),
+            ("a.b[c]", vec!["a.b", "c"], Leaf::Set),
+            ("a[b", vec!["a[b"], Leaf::Set),
+            ("a[b]c", vec!["a[b]c"], Leaf::Set),
+            ("a\\[b\\][c\\]]", vec!["a[b]", "c]"], Leaf::Set),
+        ];
+
+        for (key, expected, expected_leaf) in cases {
+            let (segments, leaf) = split(&Cow::Borrowed(key), config);
+            assert_eq!(segments, expected, "{key}");
+            assert_eq!(leaf, expected_leaf, "{key}");
+        }
+    }
+
+    #[test]
+    fn test_split_separator() {
+        let (segments, _) = split(&Cow::Borrowed("a.b__c__0"), Config::new().separator("__"));
+        assert_eq!(segments, vec!["a.b", "c", "0"]);
+    }
+
+    #[test]
+    fn test_split_escaped() {
+        let cases = [
+            ("a\\.b.c", vec!["a.b", "c"], Leaf::Set),
+            ("a.\\$length", vec!["a", "$length"], Leaf::Set),
+            ("a.$length", vec!["a"], Leaf::Length),
+            ("a\\\\.b", vec!["a\\", "b"], Leaf::Set),
+        ];
+
+        for (key, expected, expected_leaf) in cases {
+            let (segments, leaf) = split(&Cow::Borrowed(key), Config::new());
+            assert_eq!(segments, expected, "{key}");
+            assert_eq!(leaf, expected_leaf, "{key}");
+        }
+    }
+
+    #[test]
+    fn test_escape_round_trip() {
+        let configs = [
+            Config::new(),
+            Config::new().separator("__"),
+            Config::new().key_syntax(KeySyntax::Bracket),
+        ];
+        let segments = ["a.b", "$length", "a\\b", "x_", "_y", "a__b", "a[b]", "plain", "", "~", "\\~", "."];
+
+        for config in configs {
+            for segment in segments {
+                let key = join(&join("p", &escape(segment, config), config), "q", config);
+                let (got, leaf) = split(&Cow::Owned(key.clone()), config);
+                assert_eq!(got, vec!["p", segment, "q"], "{key}");
+                assert_eq!(leaf, Leaf::Set);
+            }
+        }
+
+        assert_eq!(escape("plain", Config::new()), "plain");
+        assert_eq!(escape("a.b", Config::new()), "a\\.b");
+        assert_eq!(escape("user_name", Config::new().separator("__")), "user_name");
+        assert_eq!(escape("", Config::new()), "\\~");
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!(join("", "a", Config::new()), "a");
+        assert_eq!(join("a", "b", Config::new()), "a.b");
+        assert_eq!(join("a", "b", Config::new().separator("__")), "a__b");
+        assert_eq!(join("a[b]", "0", Config::new().key_syntax(KeySyntax::Bracket)), "a[b][0]");
+    }
+}
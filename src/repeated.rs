@@ -0,0 +1,48 @@
+//! Sequences which may hold a single item, for use with `#[serde(with = "...")]`.
+//!
+//! With [repeated keys](crate::SequenceStyle::Repeated), a key found once is read as a sequence
+//! of one item. This is not the case when the target type is not known, as in flattened fields and
+//! enums, serde then reading the item alone. This helper accepts a sequence or a single item.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use serde_www_form_urlencoded::{Config, ParameterStyle};
+//!
+//! #[derive(Debug, Serialize, Deserialize, PartialEq)]
+//! struct Filter {
+//!   #[serde(with = "serde_www_form_urlencoded::repeated")]
+//!   tags: Vec<String>,
+//!   min: f64
+//! }
+//!
+//! #[derive(Debug, Serialize, Deserialize, PartialEq)]
+//! struct Query {
+//!   page: u32,
+//!   #[serde(flatten)]
+//!   filter: Filter
+//! }
+//!
+//! let config = Config::new().parameter_style(ParameterStyle::Form, true);
+//! let query = Query { page: 1, filter: Filter { tags: vec!["red".to_string()], min: 2.5 } };
+//! assert_eq!(config.to_string(&query).unwrap(), "page=1&tags=red&min=2.5");
+//! assert_eq!(config.from_str::<Query>("page=1&tags=red&min=2.5").unwrap(), query);
+//! ```
+//!
+//! As an empty sequence is not written at all, such a field should also be `#[serde(default)]`.
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::{DeserializeOwned, Error as _}};
+
+use crate::{Value, from_value};
+
+/// Serialize the sequence as-is.
+pub fn serialize<T: Serialize, S: Serializer>(items: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+    items.serialize(serializer)
+}
+
+/// Deserialize a sequence, or a single value as a sequence of one item.
+pub fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<T>, D::Error> {
+    let value = match Value::deserialize(deserializer)? {
+        item @ (Value::Single(_) | Value::Quoted(_)) => Value::Seq(vec![item]),
+        value => value,
+    };
+    from_value(value).map_err(D::Error::custom)
+}
@@ -2,131 +2,83 @@ use std::io::Write;
 
 use serde::Serialize;
 
-use crate::Error;
+use crate::{Config, Error, Map, StringStyle, Value};
 
 use super::Result;
 
 #[derive(Default)]
-pub struct Map(Vec<(String, Value)>);
-
-pub enum Value {
-    Map(Map),
-    Seq(Vec<Value>),
-    Single(String)
+pub struct FormEncoder {
+    stack: Vec<Value>,
+    config: Config,
+    /// Whether a `None` is omitted, which is not the case for the items of a sequence.
+    skip_none: bool
 }
 
-impl Value {
-    pub fn to_string(self) -> String {
-        let mut output = String::default();
-        self.write(Default::default(), &mut output);
-        if output.ends_with("&") {
-            output.pop();
-        }
-        output
-    }
-
-    fn write(self, path: String, output: &mut String) {
-        let prefix = if path.len() > 0 { format!("{path}.") } else { Default::default() };
-
-        match self {
-            Value::Map(map) => {
-                map.0.into_iter()
-                    .for_each(|(k, v)| {
-                        let cpth = format!("{}{k}", prefix);
-                        v.write(cpth, output);
-                    });
-            },
-            Value::Seq(values) => {
-                let len = values.len();
-                values.into_iter().enumerate().for_each(|(i, v)| {
-                    let cpth =  format!("{}{i}", prefix);
-                    v.write(cpth, output);
-                });
-                *output += &format!("{}$length={len}&", prefix);
-            },
-            Value::Single(value) => {
-                *output += &format!("{}={value}&", path);
-            },
-        }
-    }
-}
-
-impl Value {
-    pub fn as_single(self) -> String {
-        self.try_as_single().unwrap()
-    }
-
-    pub fn try_as_single(self) -> Option<String> {
-        match self {
-            Value::Single(value) => Some(value),
-            _ => None
-        }
-    }
-
-    pub fn try_as_mut_seq(&mut self) -> Option<&mut Vec<Value>> {
-        match self {
-            Value::Seq(value) => Some(value),
-            _ => None
-        }       
-    }
-
-    pub fn as_mut_seq(&mut self) -> &mut Vec<Value> {
-        self.try_as_mut_seq().unwrap()
-    }
-
-    pub fn try_as_map(self) -> Option<Map> {
-        match self {
-            Value::Map(map) => Some(map),
-            _ => None
+impl FormEncoder {
+    pub fn new(config: Config) -> Self {
+        Self {
+            stack: Default::default(),
+            config,
+            skip_none: config.skip_none
         }
     }
 
-    pub fn as_map(self) -> Map {
-        self.try_as_map().unwrap()
-    }
-
-    pub fn try_as_mut_map(&mut self) -> Option<&mut Map> {
-        match self {
-            Value::Map(value) => Some(value),
-            _ => None
-        }       
-    }
-    
-    pub fn as_mut_map(&mut self) -> &mut Map {
-        self.try_as_mut_map().unwrap()
-    }
-}
-
-impl From<String> for Value {
-    fn from(value: String) -> Self {
-        Self::Single(value)
+    /// Serialize the value into its flat key/value pairs, neither percent-encoded nor quoted.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_www_form_urlencoded::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Query<'a> {
+    ///   q: &'a str,
+    ///   tags: [&'a str; 1]
+    /// }
+    ///
+    /// let pairs = Serializer::default().into_pairs(&Query { q: "a&b", tags: ["c"] }).unwrap();
+    /// assert_eq!(pairs, vec![
+    ///     ("q".to_string(), "a&b".to_string()),
+    ///     ("tags.0".to_string(), "c".to_string()),
+    ///     ("tags.$length".to_string(), "1".to_string()),
+    /// ]);
+    /// ```
+    pub fn into_pairs<T: ?Sized + Serialize>(self, value: &T) -> Result<Vec<(String, String)>> {
+        let config = self.config;
+        value.serialize(self).and_then(|v| v.into_pairs_with(config))
+    }
+
+    /// Create an encoder for a nested value, sharing the same configuration.
+    fn child(&self) -> Self {
+        Self::new(self.config)
+    }
+
+    /// Create an encoder for an item of a sequence, in which a `None` is kept to preserve the indices.
+    fn element(&self) -> Self {
+        Self { skip_none: false, ..self.child() }
     }
-}
-
-#[derive(Default)]
-pub struct FormEncoder(Vec<Value>);
 
-impl FormEncoder {
     pub fn push<V: Into<Value>>(&mut self, item: V) {
-        self.0.push(item.into());
+        self.stack.push(item.into());
     }
 
     pub fn pop_key_value(&mut self) -> Result<()> {
-        let value = self.0.pop().unwrap();
-        let key = self.0.pop().unwrap().as_single();
-        
-        self.0.last_mut()
+        let value = self.stack.pop().unwrap();
+        let key = match self.stack.pop().unwrap() {
+            Value::Single(key) | Value::Quoted(key) => key,
+            key => key.to_string()
+        };
+
+        self.stack.last_mut()
+            .and_then(Value::as_map_mut)
             .unwrap()
-            .as_mut_map()
-            .0
-            .push((key, value));
+            .insert(key, value);
 
         Ok(())
     }
 
     pub fn pop_element(&mut self) -> Result<()> {
-        let value = self.0.pop().unwrap();
-        self.0.last_mut().unwrap().as_mut_seq().push(value);
+        let value = self.stack.pop().unwrap();
+        self.stack.last_mut().and_then(Value::as_seq_mut).unwrap().push(value);
         Ok(())
     }
 }
@@ -138,7 +90,7 @@ impl serde::ser::SerializeMap for FormEncoder {
     fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
         T: ?Sized + serde::Serialize {
-        let k = key.serialize(FormEncoder::default())?;
+        let k = key.serialize(self.child())?;
         self.push(k);
         Ok(())
     }
@@ -146,7 +98,7 @@ impl serde::ser::SerializeMap for FormEncoder {
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + serde::Serialize {
-        let v = value.serialize(FormEncoder::default())?;
+        let v = value.serialize(self.child())?;
         self.push(v);
         Ok(())
     }
@@ -163,7 +115,7 @@ impl serde::ser::SerializeMap for FormEncoder {
     }
 
     fn end(mut self) -> Result<Self::Ok> {
-        Ok(self.0.pop().unwrap())
+        Ok(self.stack.pop().unwrap())
     }
 }
 
@@ -174,9 +126,9 @@ impl serde::ser::SerializeStruct for FormEncoder {
     fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + serde::Serialize {
-            let v = value.serialize(FormEncoder::default())?;
+            let v = value.serialize(self.child())?;
             
-            self.push(key.to_string());
+            self.push(key);
             self.push(v);
 
             self.pop_key_value()?;
@@ -186,7 +138,7 @@ impl serde::ser::SerializeStruct for FormEncoder {
     }
 
     fn end(mut self) -> Result<Self::Ok> {
-        Ok(self.0.pop().unwrap())
+        Ok(self.stack.pop().unwrap())
     }
 }
 
@@ -197,13 +149,13 @@ impl serde::ser::SerializeSeq for FormEncoder {
     fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + serde::Serialize {
-        let element: Value = value.serialize(FormEncoder::default())?;
-        self.0.last_mut().unwrap().as_mut_seq().push(element);
+        let element: Value = value.serialize(self.element())?;
+        self.stack.last_mut().and_then(Value::as_seq_mut).unwrap().push(element);
         Ok(())
     }
 
     fn end(mut self) -> Result<Self::Ok> {
-        Ok(self.0.pop().unwrap())
+        Ok(self.stack.pop().unwrap())
     }
 }
 
@@ -214,13 +166,13 @@ impl serde::ser::SerializeTuple for FormEncoder {
     fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize {
-        let element: Value = value.serialize(FormEncoder::default())?;
-        self.0.last_mut().unwrap().as_mut_seq().push(element);
+        let element: Value = value.serialize(self.element())?;
+        self.stack.last_mut().and_then(Value::as_seq_mut).unwrap().push(element);
         Ok(())
     }
 
     fn end(mut self) -> Result<Self::Ok> {
-        Ok(self.0.pop().unwrap())
+        Ok(self.stack.pop().unwrap())
     }
 }
 
@@ -231,14 +183,14 @@ impl serde::ser::SerializeTupleStruct for FormEncoder {
     fn serialize_field<T>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
     where
         T: ?Sized + Serialize {
-            let v = value.serialize(FormEncoder::default())?;            
+            let v = value.serialize(self.element())?;            
             self.push(v);
             self.pop_element()?;            
             Ok(())
     }
 
     fn end(mut self) -> std::result::Result<Self::Ok, Self::Error> {
-        Ok(self.0.pop().unwrap())
+        Ok(self.stack.pop().unwrap())
     }
 }
 
@@ -249,14 +201,16 @@ impl serde::ser::SerializeTupleVariant for FormEncoder {
     fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize {
-        let v = value.serialize(FormEncoder::default())?;            
+        let v = value.serialize(self.element())?;            
         self.push(v);
         self.pop_element()?;   
         Ok(())
     }
 
     fn end(mut self) -> Result<Self::Ok> {
-        Ok(self.0.pop().unwrap())
+        // Nest the fields under the variant name.
+        self.pop_key_value()?;
+        Ok(self.stack.pop().unwrap())
     }
 }
 
@@ -267,15 +221,17 @@ impl serde::ser::SerializeStructVariant for FormEncoder {
     fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> std::result::Result<(), Self::Error>
     where
         T: ?Sized + Serialize {
-        let v = value.serialize(FormEncoder::default())?;       
-        self.push(Value::Single(key.to_string()));
+        let v = value.serialize(self.child())?;       
+        self.push(key);
         self.push(v);
         self.pop_key_value()?;   
         Ok(())
     }
 
     fn end(mut self) -> std::result::Result<Self::Ok, Self::Error> {
-        Ok(self.0.pop().unwrap())
+        // Nest the fields under the variant name.
+        self.pop_key_value()?;
+        Ok(self.stack.pop().unwrap())
     }
 }
 
@@ -347,21 +303,27 @@ impl serde::Serializer for FormEncoder {
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
-        let value = v.to_string().replace("\"", "\\\"");
-        Ok(format!("\"{value}\"").into())
+        match self.config.string_style {
+            StringStyle::Plain => Ok(v.into()),
+            StringStyle::Quoted => Ok(Value::Quoted(v.to_string())),
+        }
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
         let vec = v
             .iter()
-            .map(|v| v.serialize(FormEncoder::default()))
+            .map(|v| v.serialize(self.element()))
             .collect::<Result<Vec<_>>>()?;
 
         Ok(Value::Seq(vec))
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        self.serialize_unit()
+        match self.skip_none {
+            // An empty map writes no key at all.
+            true => Ok(Value::Map(Map::default())),
+            false => self.serialize_unit()
+        }
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
@@ -378,39 +340,46 @@ impl serde::Serializer for FormEncoder {
         self.serialize_unit()
     }
 
-    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str) -> Result<Self::Ok> {
-        todo!()
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Self::Ok> {
+        Ok(variant.into())
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok>
     where
         T: ?Sized + serde::Serialize {
-        value.serialize(self)
+        match name {
+            // The single values of a `Value` are never quoted.
+            crate::value::RAW => value.serialize(Self::new(self.config.string_style(StringStyle::Plain))),
+            _ => value.serialize(self)
+        }
     }
 
-    fn serialize_newtype_variant<T>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_variant<T>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<Self::Ok>
     where
         T: ?Sized + serde::Serialize {
-        value.serialize(self)
+        let value = value.serialize(self.element())?;
+        Ok(Value::Map(Map::from_iter([(variant, value)])))
     }
 
     fn serialize_seq(mut self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.0.push(Value::Seq(Default::default()));
+        self.stack.push(Value::Seq(Default::default()));
         Ok(self)
     }
 
     fn serialize_tuple(mut self, _len: usize) -> Result<Self::SerializeTuple> {
-        self.0.push(Value::Seq(Default::default()));
+        self.stack.push(Value::Seq(Default::default()));
         Ok(self)
     }
 
     fn serialize_tuple_struct(mut self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct> {
-        self.0.push(Value::Seq(Default::default()));
+        self.stack.push(Value::Seq(Default::default()));
         Ok(self)
     }
 
-    fn serialize_tuple_variant(mut self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize,) -> Result<Self::SerializeTupleVariant> {
-        self.0.push(Value::Seq(Default::default()));
+    fn serialize_tuple_variant(mut self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize,) -> Result<Self::SerializeTupleVariant> {
+        self.push(Value::Map(Default::default()));
+        self.push(variant);
+        self.stack.push(Value::Seq(Default::default()));
         Ok(self)
     }
 
@@ -423,27 +392,50 @@ impl serde::Serializer for FormEncoder {
         self.serialize_map(Some(len))
     }
 
-    fn serialize_struct_variant(mut self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize,) -> Result<Self::SerializeStructVariant> {
-        self.0.push(Value::Map(Default::default()));
+    fn serialize_struct_variant(mut self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize,) -> Result<Self::SerializeStructVariant> {
+        self.push(Value::Map(Default::default()));
+        self.push(variant);
+        self.stack.push(Value::Map(Default::default()));
         Ok(self)
     }
 }
 
+impl Config {
+    /// Serialize the value with this configuration.
+    pub fn to_string<T: Serialize>(&self, value: &T) -> Result<String> {
+        value.serialize(FormEncoder::new(*self)).and_then(|v| v.encode(*self))
+    }
+
+    /// Serialize and write the value into a byte stream with this configuration.
+    pub fn to_writer<T: Serialize, Writer: Write>(&self, value: &T, writer: &mut Writer) -> Result<()> {
+        let str = self.to_string(value)?;
+        writer.write_all(str.as_bytes()).map_err(|err| Error::IoError(err.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Serialize the value into a [`Value`] tree, which can be modified before being written.
+pub fn to_value<T: Serialize>(value: &T) -> Result<Value> {
+    value.serialize(FormEncoder::default())
+}
+
 /// Serialize the value
 pub fn to_string<T: Serialize>(value: &T) -> Result<String> {
-    value.serialize(FormEncoder::default()).map(|v| v.to_string())
+    Config::default().to_string(value)
 }
 
 /// Serialize and write the value into a byte stream.
 pub fn to_writer<T: Serialize, Writer: Write>(value: &T, writer: &mut Writer) -> Result<()> {
-    let str = to_string(value)?;
-    writer.write_all(str.as_bytes()).map_err(|err| Error::IoError(err.to_string()))?;
-    Ok(())
+    Config::default().to_writer(value, writer)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{ser::to_writer, tests::{ENCODED, fixture}, to_string};
+    use std::collections::HashMap;
+
+    use serde::Serialize;
+
+    use crate::{Config, Error, ParameterStyle, KeySyntax, SequenceLength, SequenceStyle, SpaceEncoding, StringStyle, ser::to_writer, tests::{ENCODED, ENCODED_QUOTED, Foo, Nested, fixture}, to_string};
 
     #[test]
     fn test_serialize_to_string() {
@@ -462,4 +454,140 @@ mod tests {
             std::str::from_utf8(got.as_slice()).unwrap()
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_serialize_percent_encoded() {
+        let value = Nested { arg0: "a&b=c d\"é".to_string(), arg1: 1.5 };
+        let got = to_string(&value).unwrap();
+        assert_eq!(got, "arg0=a%26b%3Dc+d%22%C3%A9&arg1=1.5");
+    }
+
+    #[test]
+    fn test_serialize_space_encoding() {
+        let value = Nested { arg0: "a b".to_string(), arg1: 1.5 };
+        let config = Config::new().space_encoding(SpaceEncoding::Percent);
+        assert_eq!(config.to_string(&value).unwrap(), "arg0=a%20b&arg1=1.5");
+    }
+
+    #[test]
+    fn test_serialize_quoted() {
+        let config = Config::new().string_style(StringStyle::Quoted);
+        let got = config.to_string(&fixture()).unwrap();
+        assert_eq!(ENCODED_QUOTED, got);
+    }
+
+    #[test]
+    fn test_serialize_repeated_keys() {
+        #[derive(Serialize)]
+        struct Filter {
+            tag: Vec<&'static str>,
+            empty: Vec<u8>,
+            items: Vec<Nested>
+        }
+
+        let value = Filter {
+            tag: vec!["a", "b c"],
+            empty: vec![],
+            items: vec![Nested { arg0: "x".to_string(), arg1: 1.5 }]
+        };
+        let config = Config::new().sequence_style(SequenceStyle::Repeated);
+        assert_eq!(
+            config.to_string(&value).unwrap(),
+            "tag=a&tag=b+c&items.0.arg0=x&items.0.arg1=1.5&items.%24length=1"
+        );
+    }
+
+    #[test]
+    fn test_serialize_bracket_keys() {
+        let config = Config::new().key_syntax(KeySyntax::Bracket);
+        let got = config.to_string(&fixture()).unwrap();
+        assert!(got.starts_with("arg0=false&"));
+        assert!(got.contains("&arg8%5Barg0%5D=nested_test&"));
+        assert!(got.ends_with("&arg9%5B1%5D%5Barg1%5D=10.5&arg9%5B%24length%5D=2"));
+        assert_eq!(config.from_str::<Foo>(&got).unwrap(), fixture());
+
+        #[derive(Serialize)]
+        struct Tags {
+            tags: Vec<&'static str>
+        }
+
+        let config = config.sequence_style(SequenceStyle::Repeated);
+        let got = config.to_string(&Tags { tags: vec!["a", "b"] }).unwrap();
+        assert_eq!(got, "tags%5B%5D=a&tags%5B%5D=b");
+    }
+
+    #[test]
+    fn test_serialize_inferred_length() {
+        let config = Config::new().sequence_length(SequenceLength::Inferred { allow_gaps: false });
+        let got = config.to_string(&fixture()).unwrap();
+        assert_eq!(got, ENCODED.trim_end_matches("&arg9.%24length=2"));
+        assert_eq!(config.from_str::<Foo>(&got).unwrap(), fixture());
+    }
+
+    #[test]
+    fn test_serialize_separator() {
+        let config = Config::new().separator(":").length_key("len");
+        let got = config.to_string(&fixture()).unwrap();
+        assert!(got.contains("&arg8%3Aarg0=nested_test&"));
+        assert!(got.ends_with("&arg9%3A1%3Aarg1=10.5&arg9%3Alen=2"));
+        assert_eq!(config.from_str::<Foo>(&got).unwrap(), fixture());
+    }
+
+    #[test]
+    fn test_serialize_delimited() {
+        #[derive(Serialize)]
+        struct Query {
+            ids: Vec<u32>,
+            scope: Vec<&'static str>,
+            items: Vec<Nested>
+        }
+
+        let value = Query { ids: vec![1, 2, 3], scope: vec!["read", "write"], items: vec![] };
+        let got = Config::new().sequence_style(SequenceStyle::Delimited(' ')).to_string(&value).unwrap();
+        assert_eq!(got, "ids=1+2+3&scope=read+write&items.%24length=0");
+
+        let value = Query { ids: vec![], scope: vec!["a,b", "c"], items: vec![] };
+        let err = Config::new().sequence_style(SequenceStyle::Delimited(',')).to_string(&value).unwrap_err();
+        assert!(matches!(err.kind(), Error::DelimiterInValue { delimiter: ',' }));
+        assert_eq!(err.path(), Some("scope"));
+
+        // Only the delimiter joining the items is left as-is.
+        let got = Config::new().sequence_style(SequenceStyle::Delimited('|')).to_string(&value).unwrap();
+        assert_eq!(got, "ids.%24length=0&scope=a%2Cb|c&items.%24length=0");
+
+        #[derive(Serialize)]
+        struct Filter {
+            filter: HashMap<&'static str, &'static str>
+        }
+
+        let value = Filter { filter: HashMap::from([("role", "a|b")]) };
+        let config = Config::new().parameter_style(ParameterStyle::PipeDelimited, false);
+        assert!(matches!(config.to_string(&value).unwrap_err().kind(), Error::DelimiterInValue { delimiter: '|' }));
+
+        #[derive(Serialize)]
+        struct Ids {
+            #[serde(with = "crate::delimited::comma")]
+            ids: Vec<&'static str>
+        }
+
+        assert!(to_string(&Ids { ids: vec!["a,b", "c"] }).is_err());
+    }
+
+    #[test]
+    fn test_serialize_skip_none() {
+        #[derive(Serialize)]
+        struct Form {
+            name: Option<&'static str>,
+            age: Option<u8>,
+            nested: Option<Nested>,
+            items: Vec<Option<u8>>
+        }
+
+        let value = Form { name: None, age: Some(3), nested: None, items: vec![None, Some(1)] };
+        assert_eq!(to_string(&value).unwrap(), "name=null&age=3&nested=null&items.0=null&items.1=1&items.%24length=2");
+
+        let config = Config::new().skip_none(true);
+        assert_eq!(config.to_string(&value).unwrap(), "age=3&items.0=null&items.1=1&items.%24length=2");
+        assert_eq!(config.to_string(&Option::<Nested>::None).unwrap(), "");
+    }
+}
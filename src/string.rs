@@ -0,0 +1,42 @@
+//! Strings which may look like numbers or bools, for use with `#[serde(with = "...")]`.
+//!
+//! When the target type is not known, as in internally tagged and untagged enums or flattened
+//! fields, the types of values are [inferred](crate::Config::infer_types), and serde cannot read
+//! an inferred number, bool or `null` back into a `String`. This helper accepts them as strings.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Serialize, Deserialize, PartialEq)]
+//! #[serde(tag = "type")]
+//! enum Event {
+//!   Created {
+//!     id: u32,
+//!     #[serde(with = "serde_www_form_urlencoded::string")]
+//!     name: String
+//!   }
+//! }
+//!
+//! let event = Event::Created { id: 1, name: "42".to_string() };
+//! assert_eq!(serde_www_form_urlencoded::to_string(&event).unwrap(), "type=Created&id=1&name=42");
+//! assert_eq!(serde_www_form_urlencoded::from_str::<Event>("type=Created&id=1&name=42").unwrap(), event);
+//! ```
+//!
+//! Numbers are read back as written when the field is reached directly, but in their canonical
+//! form once buffered by serde, as in enums and flattened fields: `2.50` is then read as `2.5`.
+use serde::{Deserialize, Deserializer, Serializer, de::Error as _};
+
+use crate::Value;
+
+/// Serialize the string as-is.
+pub fn serialize<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(value)
+}
+
+/// Deserialize a string, or a number, bool or unit written as a string.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Single(value) | Value::Quoted(value) => Ok(value),
+        _ => Err(D::Error::custom("expecting a string")),
+    }
+}
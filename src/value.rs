@@ -0,0 +1,745 @@
+use std::{borrow::Cow, collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize, de::{Unexpected, Visitor}, ser::{SerializeMap, SerializeSeq}};
+
+use crate::{Config, Error, ObjectStyle, SequenceLength, SequenceStyle, StringStyle, encoding, path};
+
+/// Name of the newtype struct through which this crate reads and writes a [`Value`] as-is.
+pub(crate) const RAW: &str = "$serde_www_form_urlencoded::Value";
+
+/// A www-form-urlencoded document, or a part of it.
+///
+/// It allows to inspect and rewrite any form without a target type, values being kept as written.
+///
+/// ```
+/// use serde_www_form_urlencoded::Value;
+///
+/// let mut form: Value = serde_www_form_urlencoded::from_str("user.name=Jane&user.age=32").unwrap();
+/// assert_eq!(form["user.name"], Value::from("Jane"));
+///
+/// form["user.age"] = Value::from("33");
+/// assert_eq!(serde_www_form_urlencoded::to_string(&form).unwrap(), "user.name=Jane&user.age=33");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Single(String),
+    /// A single value written in double quotes, see [`StringStyle::Quoted`].
+    Quoted(String),
+    Map(Map),
+    Seq(Vec<Value>),
+}
+
+impl Value {
+    pub fn is_single(&self) -> bool {
+        matches!(self, Value::Single(_) | Value::Quoted(_))
+    }
+
+    pub fn is_quoted(&self) -> bool {
+        matches!(self, Value::Quoted(_))
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(self, Value::Map(_))
+    }
+
+    pub fn is_seq(&self) -> bool {
+        matches!(self, Value::Seq(_))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Single(value) | Value::Quoted(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Map> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            Value::Map(map) => Some(map),
+            _ => None
+        }
+    }
+
+    pub fn as_seq(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Seq(values) => Some(values),
+            _ => None
+        }
+    }
+
+    pub fn as_seq_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Seq(values) => Some(values),
+            _ => None
+        }
+    }
+
+    /// Borrow the value at the given path segment.
+    ///
+    /// Sequences are indexed by the position of their items.
+    fn child(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Single(_) | Value::Quoted(_) => None,
+            Value::Map(map) => map.get(key),
+            Value::Seq(values) => key.parse::<usize>().ok().and_then(|i| values.get(i)),
+        }
+    }
+
+    fn child_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match self {
+            Value::Single(_) | Value::Quoted(_) => None,
+            Value::Map(map) => map.get_mut(key),
+            Value::Seq(values) => key.parse::<usize>().ok().and_then(|i| values.get_mut(i)),
+        }
+    }
+
+    /// Borrow the value at the given dotted path, such as `user.addresses.0.city`.
+    ///
+    /// A key containing a dot is escaped with a backslash (`a\.b`), as in [`leaves`](Value::leaves).
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        split_path(path).iter().try_fold(self, |value, key| value.child(key))
+    }
+
+    /// Mutably borrow the value at the given dotted path.
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut Value> {
+        split_path(path).iter().try_fold(self, |value, key| value.child_mut(key))
+    }
+
+    /// Set the value at the given dotted path, creating the missing maps along the way.
+    ///
+    /// A single value found along the path is replaced by a map, and an item can be appended
+    /// to a sequence by using its length as index. A sequence is never replaced: nothing is
+    /// inserted if the path goes through a sequence with a key which is not one of its indices.
+    ///
+    /// Returns the previous value at this path, if any.
+    pub fn insert<V: Into<Value>>(&mut self, path: &str, value: V) -> Option<Value> {
+        let segments = split_path(path);
+        let (key, parents) = segments.split_last()?;
+        let parent = self.get_or_insert_map(parents)?;
+
+        match parent {
+            Value::Seq(values) => match key.parse::<usize>() {
+                Ok(i) if i < values.len() => Some(std::mem::replace(&mut values[i], value.into())),
+                Ok(i) if i == values.len() => {
+                    values.push(value.into());
+                    None
+                },
+                _ => None
+            },
+            Value::Map(map) => map.insert(key.as_ref(), value),
+            Value::Single(_) | Value::Quoted(_) => Some(std::mem::replace(parent, Value::Map(Map::from_iter([(key.as_ref(), value)])))),
+        }
+    }
+
+    /// Remove the value at the given dotted path.
+    ///
+    /// The following items of a sequence are shifted to fill the gap.
+    pub fn remove(&mut self, path: &str) -> Option<Value> {
+        let segments = split_path(path);
+        let (key, parents) = segments.split_last()?;
+        let parent = parents.iter().try_fold(self, |value, key| value.child_mut(key))?;
+
+        match parent {
+            Value::Single(_) | Value::Quoted(_) => None,
+            Value::Map(map) => map.remove(key),
+            Value::Seq(values) => key.parse::<usize>().ok()
+                .filter(|i| *i < values.len())
+                .map(|i| values.remove(i)),
+        }
+    }
+
+    /// Iterate over every single value with its full dotted path, in order.
+    ///
+    /// ```
+    /// use serde_www_form_urlencoded::Value;
+    ///
+    /// let form: Value = serde_www_form_urlencoded::from_str("user.name=Jane&user.tags.0=a&user.tags.%24length=1").unwrap();
+    /// let leaves: Vec<_> = form.leaves().collect();
+    /// assert_eq!(leaves, vec![("user.name".to_string(), "Jane"), ("user.tags.0".to_string(), "a")]);
+    /// ```
+    pub fn leaves(&self) -> Leaves<'_> {
+        Leaves { stack: vec![(String::default(), self)] }
+    }
+
+    /// Mutably borrow the value at the given path segments, creating it as an empty map if it is missing.
+    ///
+    /// Returns none if the path goes through a sequence with a key which is not one of its indices.
+    fn get_or_insert_map(&mut self, segments: &[Cow<'_, str>]) -> Option<&mut Value> {
+        segments.iter().try_fold(self, |value, key| {
+            if value.child(key).is_none() {
+                value.insert(&path::escape(key, Config::default()), Map::new());
+            }
+            value.child_mut(key)
+        })
+    }
+
+    /// Flatten the value into its key/value pairs, neither percent-encoded nor quoted.
+    pub fn into_pairs(self) -> Vec<(String, String)> {
+        self.into_pairs_with(Config::default()).expect("the default configuration joins no values")
+    }
+
+    /// Flatten the value into its key/value pairs, following the sequence style of the configuration.
+    pub(crate) fn into_pairs_with(self, config: Config) -> crate::Result<Vec<(String, String)>> {
+        let mut pairs = Vec::default();
+        self.flatten(config, String::default(), &mut pairs)?;
+        Ok(pairs.into_iter().map(|pair| (pair.key, pair.items.join(&pair.delimiter.to_string()))).collect())
+    }
+
+    fn flatten(self, config: Config, path: String, pairs: &mut Vec<Pair>) -> crate::Result<()> {
+        match self {
+            Value::Map(map) if !path.is_empty() && !map.is_empty() && map.values().all(Value::is_single)
+                && let ObjectStyle::Delimited(delimiter) = config.object_style => {
+                let quoted = map.values().any(Value::is_quoted);
+                let items = map.into_iter().flat_map(|(k, v)| [k, v.as_str().unwrap_or_default().to_string()]);
+                pairs.push(Pair::delimited(path, items.collect(), delimiter)?.quoted(quoted));
+            },
+            Value::Map(map) => {
+                for (k, v) in map {
+                    let path = match (&v, config.object_style) {
+                        // The nested map is written as if it was the top-level one.
+                        (Value::Map(_), ObjectStyle::Flattened) if path.is_empty() => String::default(),
+                        _ => path::join(&path, &path::escape(&k, config), config),
+                    };
+                    v.flatten(config, path, pairs)?;
+                }
+            },
+            Value::Seq(values) if config.sequence_style == SequenceStyle::Repeated && values.iter().all(Value::is_single) => {
+                let key = path::repeated(&path, config);
+                for v in values {
+                    v.flatten(config, key.clone(), pairs)?;
+                }
+            },
+            Value::Seq(values) if !values.is_empty() && values.iter().all(Value::is_single)
+                && let SequenceStyle::Delimited(delimiter) = config.sequence_style => {
+                let quoted = values.iter().any(Value::is_quoted);
+                let items = values.iter().filter_map(Value::as_str).map(str::to_string);
+                pairs.push(Pair::delimited(path, items.collect(), delimiter)?.quoted(quoted));
+            },
+            Value::Seq(values) => {
+                let len = values.len();
+                for (i, v) in values.into_iter().enumerate() {
+                    v.flatten(config, path::join(&path, &i.to_string(), config), pairs)?;
+                }
+                if config.sequence_length == SequenceLength::Explicit {
+                    pairs.push(Pair::single(path::join(&path, config.length_key, config), len.to_string()));
+                }
+            },
+            Value::Single(value) => pairs.push(Pair::single(path, value)),
+            Value::Quoted(value) => pairs.push(Pair::single(path, value).quoted(true)),
+        }
+        Ok(())
+    }
+
+    /// Write the value in the www-form-urlencoded format.
+    pub(crate) fn encode(self, config: Config) -> crate::Result<String> {
+        let mut pairs = Vec::default();
+        self.flatten(config, String::default(), &mut pairs)?;
+        let pairs = pairs.iter().map(|pair| {
+            let key = encoding::encode(&pair.key, config.space_encoding);
+            let value = pair.items.iter()
+                .map(|item| encoding::encode(item, config.space_encoding))
+                .collect::<Vec<_>>()
+                .join(&encoding::encode_delimiter(pair.delimiter, config.space_encoding));
+            match config.string_style {
+                StringStyle::Quoted if pair.quoted => format!("{key}=\"{value}\""),
+                _ => format!("{key}={value}"),
+            }
+        });
+        Ok(pairs.collect::<Vec<_>>().join("&"))
+    }
+}
+
+/// Split a dotted path into its unescaped segments.
+fn split_path(path: &str) -> Vec<Cow<'_, str>> {
+    path::split_dotted(path, Config::default())
+}
+
+/// A flat key/value pair, whose value is made of items joined by a delimiter.
+struct Pair {
+    key: String,
+    items: Vec<String>,
+    delimiter: char,
+    /// Whether the value is written in double quotes with the quoted string style.
+    quoted: bool
+}
+
+impl Pair {
+    fn single(key: String, value: String) -> Self {
+        Self { key, items: vec![value], delimiter: ',', quoted: false }
+    }
+
+    fn quoted(mut self, quoted: bool) -> Self {
+        self.quoted = quoted;
+        self
+    }
+
+    /// Join the items by the delimiter, which none of them may contain as they could not be split back.
+    fn delimited(key: String, items: Vec<String>, delimiter: char) -> crate::Result<Self> {
+        if items.iter().any(|item| item.contains(delimiter)) {
+            return Err(Error::DelimiterInValue { delimiter }.in_field(&key))
+        }
+        Ok(Self { key, items, delimiter, quoted: false })
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.clone().encode(Config::default()).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Single(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Single(value.to_string())
+    }
+}
+
+impl From<Map> for Value {
+    fn from(value: Map) -> Self {
+        Self::Map(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Self::Seq(value)
+    }
+}
+
+/// Index a value by its dotted path.
+///
+/// Panics if there is no value at this path.
+impl std::ops::Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, path: &str) -> &Self::Output {
+        self.get(path).unwrap_or_else(|| panic!("no value at path `{path}`"))
+    }
+}
+
+impl std::ops::IndexMut<&str> for Value {
+    fn index_mut(&mut self, path: &str) -> &mut Self::Output {
+        self.get_mut(path).unwrap_or_else(|| panic!("no value at path `{path}`"))
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer {
+        match self {
+            Value::Single(value) => serializer.serialize_newtype_struct(RAW, value),
+            Value::Quoted(value) => serializer.serialize_str(value),
+            Value::Map(map) => {
+                let mut ser = serializer.serialize_map(Some(map.len()))?;
+                for (k, v) in map.iter() {
+                    ser.serialize_entry(k, v)?;
+                }
+                ser.end()
+            },
+            Value::Seq(values) => {
+                let mut ser = serializer.serialize_seq(Some(values.len()))?;
+                for v in values {
+                    ser.serialize_element(v)?;
+                }
+                ser.end()
+            },
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a www-form-urlencoded value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E>
+    where
+        E: serde::de::Error {
+        Ok(Value::Single(v.to_string()))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E>
+    where
+        E: serde::de::Error {
+        Ok(Value::Single(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E>
+    where
+        E: serde::de::Error {
+        Ok(Value::Single(v.to_string()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E>
+    where
+        E: serde::de::Error {
+        Ok(Value::Single(v.to_string()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E>
+    where
+        E: serde::de::Error {
+        Ok(Value::Single(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E>
+    where
+        E: serde::de::Error {
+        Ok(Value::Single(v))
+    }
+
+    /// Quoted strings are visited as bytes by this crate.
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E>
+    where
+        E: serde::de::Error {
+        String::from_utf8(v.to_vec())
+            .map(Value::Quoted)
+            .map_err(|_| E::invalid_value(Unexpected::Bytes(v), &self))
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E>
+    where
+        E: serde::de::Error {
+        self.visit_unit()
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E>
+    where
+        E: serde::de::Error {
+        Ok(Value::Single("null".to_string()))
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: serde::Deserializer<'de> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: serde::Deserializer<'de> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Seq(values))
+    }
+
+    fn visit_map<A>(self, mut access: A) -> Result<Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de> {
+        let mut map = Map::new();
+        while let Some((k, v)) = access.next_entry::<String, Value>()? {
+            map.insert(k, v);
+        }
+        Ok(Value::Map(map))
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de> {
+        deserializer.deserialize_newtype_struct(RAW, ValueVisitor)
+    }
+}
+
+/// Iterator over the single values of a [`Value`] with their dotted path, see [`Value::leaves`].
+pub struct Leaves<'a> {
+    stack: Vec<(String, &'a Value)>
+}
+
+impl<'a> Iterator for Leaves<'a> {
+    type Item = (String, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let join = |path: &str, key: &str| path::join(path, &path::escape(key, Config::default()), Config::default());
+
+        loop {
+            let (path, value) = self.stack.pop()?;
+            match value {
+                Value::Single(value) | Value::Quoted(value) => return Some((path, value)),
+                Value::Map(map) => self.stack.extend(
+                    map.iter().rev().map(|(k, v)| (join(&path, k), v))
+                ),
+                Value::Seq(values) => self.stack.extend(
+                    values.iter().enumerate().rev().map(|(i, v)| (join(&path, &i.to_string()), v))
+                ),
+            }
+        }
+    }
+}
+
+/// A map of values, keeping its entries in insertion order.
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    /// The entries in insertion order, or none for the removed ones.
+    entries: Vec<Option<(String, Value)>>,
+    /// Position of each key in `entries`.
+    index: HashMap<String, usize>
+}
+
+/// Maps are equal if they hold the same entries in the same order.
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Map {}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let i = *self.index.get(key)?;
+        self.entries[i].as_ref().map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let i = *self.index.get(key)?;
+        self.entries[i].as_mut().map(|(_, v)| v)
+    }
+
+    /// Insert a value, keeping the position of the key if it is already present.
+    ///
+    /// Returns the previous value of the key, if any.
+    pub fn insert<K: Into<String>, V: Into<Value>>(&mut self, key: K, value: V) -> Option<Value> {
+        let key = key.into();
+        match self.get_mut(&key) {
+            Some(current) => Some(std::mem::replace(current, value.into())),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push(Some((key, value.into())));
+                None
+            }
+        }
+    }
+
+    /// Remove a key, preserving the order of the other entries.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let i = self.index.remove(key)?;
+        self.entries[i].take().map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&String, &Value)> {
+        self.entries.iter().flatten().map(|(k, v)| (k, v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (&String, &mut Value)> {
+        self.entries.iter_mut().flatten().map(|(k, v)| (&*k, v))
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &String> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &Value> {
+        self.iter().map(|(_, v)| v)
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (String, Value);
+    type IntoIter = std::iter::Flatten<std::vec::IntoIter<Option<(String, Value)>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter().flatten()
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>> FromIterator<(K, V)> for Map {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = Map::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, KeySyntax, StringStyle, from_str, tests::{ENCODED, ENCODED_QUOTED, fixture}, to_string};
+    use super::{Map, Value};
+
+    #[test]
+    fn test_value_round_trip() {
+        let value = from_str::<Value>(ENCODED).unwrap();
+        assert_eq!(value["arg8.arg0"], Value::from("nested_test"));
+        assert_eq!(value["arg9.1.arg1"].as_str(), Some("10.5"));
+        assert_eq!(to_string(&value).unwrap(), ENCODED);
+        assert_eq!(value.to_string(), ENCODED);
+    }
+
+    #[test]
+    fn test_value_from_serializer() {
+        let value = from_str::<Value>(&to_string(&fixture()).unwrap()).unwrap();
+        let mut expected = from_str::<Value>(ENCODED).unwrap();
+        assert_eq!(value, expected);
+
+        expected["arg7"] = Value::from("changed");
+        assert_eq!(expected["arg7"], Value::from("changed"));
+
+        let config = Config::new().string_style(StringStyle::Quoted);
+        let quoted = config.from_str::<Value>(ENCODED_QUOTED).unwrap();
+        assert_eq!(quoted["arg7"], Value::Quoted("test".to_string()));
+        assert_eq!(quoted["arg9.0.arg1"], Value::from("20.5"));
+        assert_eq!(config.to_string(&quoted).unwrap(), ENCODED_QUOTED);
+    }
+
+    #[test]
+    fn test_value_index_seq() {
+        let value = Value::from(Map::from_iter([
+            ("tags", Value::Seq(vec![Value::from("a"), Value::from("b")]))
+        ]));
+        assert_eq!(value["tags.1"], Value::from("b"));
+        assert_eq!(to_string(&value).unwrap(), "tags.0=a&tags.1=b&tags.%24length=2");
+    }
+
+    #[test]
+    fn test_map_order() {
+        let mut map = Map::from_iter([("a", "1"), ("b", "2"), ("c", "3")]);
+        assert_eq!(map.remove("b"), Some(Value::from("2")));
+        assert_eq!(map.insert("a", "4"), Some(Value::from("1")));
+        assert_eq!(map.insert("b", "5"), None);
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec!["a", "c", "b"]);
+        assert_eq!(map, Map::from_iter([("a", "4"), ("c", "3"), ("b", "5")]));
+        assert_ne!(map, Map::from_iter([("a", "4"), ("b", "5"), ("c", "3")]));
+    }
+
+    #[test]
+    fn test_value_path_api() {
+        let mut value = from_str::<Value>("user.name=Jane&user.password=secret&user.addresses.0.city=Paris&user.addresses.%24length=1").unwrap();
+        assert_eq!(value.get("user.addresses.0.city").and_then(Value::as_str), Some("Paris"));
+        assert_eq!(value.get("user.addresses.1.city"), None);
+        assert_eq!(value.get("user.name.first"), None);
+
+        assert_eq!(value.remove("user.password"), Some(Value::from("secret")));
+        assert_eq!(value.remove("user.password"), None);
+
+        *value.get_mut("user.name").unwrap() = Value::from("John");
+        assert_eq!(value.insert("user.addresses.0.city", "Lyon"), Some(Value::from("Paris")));
+        assert_eq!(value.insert("user.addresses.1.city", "Nice"), None);
+        assert_eq!(value.insert("meta.source", "web"), None);
+
+        let leaves = value.leaves().collect::<Vec<_>>();
+        assert_eq!(leaves, vec![
+            ("user.name".to_string(), "John"),
+            ("user.addresses.0.city".to_string(), "Lyon"),
+            ("user.addresses.1.city".to_string(), "Nice"),
+            ("meta.source".to_string(), "web"),
+        ]);
+
+        assert_eq!(value.remove("user.addresses.0"), Some(Value::from(Map::from_iter([("city", "Lyon")]))));
+        assert_eq!(value["user.addresses.0.city"], Value::from("Nice"));
+    }
+
+    #[test]
+    fn test_value_insert_replaces_single() {
+        let mut value = from_str::<Value>("a=1").unwrap();
+        assert_eq!(value.insert("a.b", "2"), Some(Value::from("1")));
+        assert_eq!(to_string(&value).unwrap(), "a.b=2");
+    }
+
+    #[test]
+    fn test_value_insert_keeps_seq() {
+        let mut value = from_str::<Value>("tags.0=a&tags.1=b&tags.%24length=2").unwrap();
+        for path in ["tags.5", "tags.foo", "tags.foo.bar", "tags.5.bar"] {
+            assert_eq!(value.insert(path, "z"), None, "{path}");
+            assert_eq!(to_string(&value).unwrap(), "tags.0=a&tags.1=b&tags.%24length=2", "{path}");
+        }
+
+        assert_eq!(value.insert("tags.2.name", "c"), None);
+        assert_eq!(value["tags.2.name"], Value::from("c"));
+    }
+
+    #[test]
+    fn test_value_escaped_path() {
+        let mut value = Value::from(Map::from_iter([
+            ("a.b", Value::from(Map::from_iter([("$length", "1"), ("c\\d", "2")])))
+        ]));
+
+        let leaves = value.leaves().collect::<Vec<_>>();
+        assert_eq!(leaves, vec![("a\\.b.\\$length".to_string(), "1"), ("a\\.b.c\\\\d".to_string(), "2")]);
+        for (path, leaf) in leaves {
+            assert_eq!(value.get(&path).and_then(Value::as_str), Some(leaf), "{path}");
+        }
+
+        assert_eq!(value.insert("a\\.b.e\\.f", "3"), None);
+        assert_eq!(value.get("a\\.b").and_then(|v| v.as_map()).and_then(|m| m.get("e.f")), Some(&Value::from("3")));
+        assert_eq!(value.remove("a\\.b.e\\.f"), Some(Value::from("3")));
+        assert_eq!(value.get("a.b"), None);
+    }
+
+    #[test]
+    fn test_value_empty_keys() {
+        let cases = [
+            (Config::new(), Map::from_iter([("", Value::from(Map::from_iter([("x", "1")])))])),
+            (Config::new().key_syntax(KeySyntax::Bracket), Map::from_iter([("a", Value::from(Map::from_iter([("", "1")])))])),
+        ];
+
+        for (config, map) in cases {
+            let value = Value::from(map);
+            let encoded = config.to_string(&value).unwrap();
+            assert_eq!(config.from_str::<Value>(&encoded).unwrap(), value, "{encoded}");
+        }
+    }
+
+    #[test]
+    fn test_value_as_written() {
+        // Values are neither inferred nor read as sequences.
+        for input in ["zip.0=75001", "price=9.50&ratio=1.0&id=007&big=1e3&flag=true&none=null"] {
+            let value = from_str::<Value>(input).unwrap();
+            assert_eq!(to_string(&value).unwrap(), input);
+        }
+        assert_eq!(from_str::<Value>("zip.0=75001").unwrap()["zip"], Value::Map(Map::from_iter([("0", "75001")])));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_value_index_missing() {
+        let value = from_str::<Value>("a=1").unwrap();
+        let _ = &value["b"];
+    }
+}